@@ -0,0 +1,65 @@
+//! Бенчмарки обхода и записи на синтетическом дереве: последовательно против параллельно.
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ginpee::collector::{self, CollectOptions};
+use ginpee::{formatter, writer};
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Дерево `dirs` × `files` файлов по ~4 КиБ, похожее на исходники монорепозитория.
+fn synthetic_tree(dirs: usize, files: usize) -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let body = "fn main() { println!(\"hello\"); }\n".repeat(120);
+    for dir in 0..dirs {
+        let path = temp_dir.path().join(format!("crates/c{}/src", dir));
+        fs::create_dir_all(&path).unwrap();
+        for file in 0..files {
+            fs::write(path.join(format!("m{}.rs", file)), &body).unwrap();
+        }
+    }
+    fs::write(temp_dir.path().join(".gpskip"), "target/\n").unwrap();
+    temp_dir
+}
+
+fn collect(root: &Path, jobs: usize) -> collector::Collection {
+    let patterns = vec!["*.rs".to_string()];
+    let options = CollectOptions { jobs, ..CollectOptions::default() };
+    collector::collect(root, &patterns, &options).unwrap()
+}
+
+fn bench_walk(c: &mut Criterion) {
+    let tree = synthetic_tree(200, 50);
+    let mut group = c.benchmark_group("walk");
+    group.sample_size(10);
+    for jobs in [1, 0] {
+        group.bench_with_input(BenchmarkId::from_parameter(jobs_label(jobs)), &jobs, |b, &jobs| {
+            b.iter(|| black_box(collect(tree.path(), jobs)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_write(c: &mut Criterion) {
+    let tree = synthetic_tree(200, 50);
+    let collection = collect(tree.path(), 0);
+    let layout = formatter::layout(&collection.files, &collection.links, tree.path()).unwrap();
+    let out_dir = TempDir::new().unwrap();
+    let output = out_dir.path().join("project.md");
+
+    let mut group = c.benchmark_group("write");
+    group.sample_size(10);
+    for jobs in [1, 0] {
+        group.bench_with_input(BenchmarkId::from_parameter(jobs_label(jobs)), &jobs, |b, &jobs| {
+            b.iter(|| writer::write_layout(&output, None, &layout, None, jobs).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn jobs_label(jobs: usize) -> &'static str {
+    if jobs == 1 { "sequential" } else { "parallel" }
+}
+
+criterion_group!(benches, bench_walk, bench_write);
+criterion_main!(benches);
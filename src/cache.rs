@@ -0,0 +1,210 @@
+//! Модуль дискового кэша обработанного содержимого файлов (`.ginpee/cache`).
+//!
+//! Запись ищется по пути и способу обработки; если размер и mtime не изменились,
+//! файл даже не читается, а если изменился только mtime — сверяется хеш содержимого.
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+
+/// Служебная директория ginpee в корне проекта; обход в неё никогда не заходит.
+pub const GINPEE_DIR: &str = ".ginpee";
+
+/// Директория кэша относительно корня проекта.
+pub const CACHE_DIR: &str = ".ginpee/cache";
+
+#[derive(Debug)]
+pub struct Cache {
+    dir: PathBuf,
+    reused: AtomicUsize,
+    processed: AtomicUsize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    path: PathBuf,
+    size: u64,
+    mtime_ns: u64,
+    hash: String,
+    output: String,
+}
+
+impl Cache {
+    /// Кэш в `dir`; директория создаётся при первой записи.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            reused: AtomicUsize::new(0),
+            processed: AtomicUsize::new(0),
+        }
+    }
+
+    /// Результат обработки `source`: из кэша, если файл не менялся, иначе через `process`.
+    ///
+    /// `processing` различает способы обработки одного файла (например, полный текст и outline).
+    pub fn load(
+        &self,
+        source: &Path,
+        processing: &str,
+        process: impl FnOnce(String) -> Result<String, Box<dyn std::error::Error>>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let (size, mtime_ns) = stamp(source)?;
+
+        let record_path = self.record_path(source, processing);
+        let cached: Option<Record> = fs::read(&record_path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .filter(|record: &Record| record.path == source);
+        if let Some(record) = &cached
+            && record.size == size
+            && record.mtime_ns == mtime_ns
+        {
+            self.reused.fetch_add(1, Ordering::Relaxed);
+            return Ok(record.output.clone());
+        }
+
+        let content = fs::read_to_string(source)?;
+        let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        // Файл «тронули», но не изменили (checkout, touch): обработка не нужна
+        let output = match cached {
+            Some(record) if record.hash == hash => {
+                self.reused.fetch_add(1, Ordering::Relaxed);
+                record.output
+            }
+            _ => {
+                self.processed.fetch_add(1, Ordering::Relaxed);
+                process(content)?
+            }
+        };
+
+        let record = Record { path: source.to_path_buf(), size, mtime_ns, hash, output };
+        // Кэш — только ускорение: не получилось записать, значит, в следующий раз обработаем заново
+        let _ = self.store(&record_path, &record);
+        Ok(record.output)
+    }
+
+    /// Сколько файлов взято из кэша и сколько обработано заново.
+    pub fn stats(&self) -> (usize, usize) {
+        (self.reused.load(Ordering::Relaxed), self.processed.load(Ordering::Relaxed))
+    }
+
+    /// Удаляет устаревшие записи: исходный файл удалён или изменился (размер или mtime не совпадают),
+    /// либо запись не читается; возвращает, сколько удалено.
+    ///
+    /// Записи, просто не понадобившиеся в этом запуске (другой профиль, другой конфиг, шаблон без
+    /// `file.content`), остаются — иначе чередование запусков каждый раз обрабатывало бы всё заново.
+    pub fn prune(&self) -> usize {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return 0;
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json") && is_stale(path))
+            .filter(|path| fs::remove_file(path).is_ok())
+            .count()
+    }
+
+    fn record_path(&self, source: &Path, processing: &str) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update([0]);
+        hasher.update(processing);
+        hasher.update([0]);
+        hasher.update(source.as_os_str().as_encoded_bytes());
+        self.dir.join(format!("{:x}.json", hasher.finalize()))
+    }
+
+    fn store(&self, record_path: &Path, record: &Record) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.dir.is_dir() {
+            if let Some(parent) = self.dir.parent() {
+                create_ginpee_dir(parent)?;
+            }
+            fs::create_dir_all(&self.dir)?;
+        }
+        // Запись через временный файл: параллельные чтения не увидят половину JSON
+        let temp = record_path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temp, serde_json::to_vec(record)?)?;
+        fs::rename(&temp, record_path)?;
+        Ok(())
+    }
+}
+
+/// Размер и mtime файла (в наносекундах) — по ним узнаётся, что файл не менялся.
+fn stamp(path: &Path) -> std::io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let mtime_ns = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_nanos() as u64);
+    Ok((metadata.len(), mtime_ns))
+}
+
+fn is_stale(record_path: &Path) -> bool {
+    let Some(record) = fs::read(record_path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Record>(&bytes).ok())
+    else {
+        return true;
+    };
+    stamp(&record.path).map_or(true, |stamp| stamp != (record.size, record.mtime_ns))
+}
+
+/// Создаёт служебную директорию `.ginpee`; её файлы не должны появляться в `git status`.
+pub fn create_ginpee_dir(dir: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let gitignore = dir.join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(gitignore, "*\n")?;
+    }
+    Ok(())
+}
+
+/// `ginpee cache clear`: удаляет кэш; возвращает, был ли он.
+pub fn clear(dir: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    if !dir.exists() {
+        return Ok(false);
+    }
+    fs::remove_dir_all(dir).map_err(|e| format!("failed to remove {}: {}", dir.display(), e))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_reuses_unchanged_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("main.rs");
+        fs::write(&source, "fn main() {}").unwrap();
+        let cache = Cache::new(temp_dir.path().join(CACHE_DIR));
+        let upper = |content: String| Ok(content.to_uppercase());
+
+        assert_eq!(cache.load(&source, "upper", upper).unwrap(), "FN MAIN() {}");
+        assert_eq!(cache.load(&source, "upper", |_| panic!("must be cached")).unwrap(), "FN MAIN() {}");
+        assert_eq!(cache.stats(), (1, 1));
+        assert!(temp_dir.path().join(".ginpee/.gitignore").exists());
+
+        fs::write(&source, "fn main() { run() }").unwrap();
+        assert_eq!(cache.load(&source, "upper", upper).unwrap(), "FN MAIN() { RUN() }");
+        assert_eq!(cache.load(&source, "raw", Ok).unwrap(), "fn main() { run() }");
+
+        // Записи, не понадобившиеся этому запуску, остаются; удаляются только устаревшие
+        let other = temp_dir.path().join("other.rs");
+        fs::write(&other, "fn other() {}").unwrap();
+        cache.load(&other, "raw", Ok).unwrap();
+        fs::remove_file(&other).unwrap();
+        let cache = Cache::new(temp_dir.path().join(CACHE_DIR));
+        cache.load(&source, "raw", |_| panic!("must be cached")).unwrap();
+        assert_eq!(cache.prune(), 1);
+        assert_eq!(fs::read_dir(temp_dir.path().join(CACHE_DIR)).unwrap().count(), 2);
+
+        assert!(clear(&temp_dir.path().join(CACHE_DIR)).unwrap());
+        assert!(!clear(&temp_dir.path().join(CACHE_DIR)).unwrap());
+    }
+}
@@ -1,53 +1,764 @@
 //! Модуль сбора файлов с учётом `.gpskip` и фильтров.
 use glob::Pattern;
-use ignore::WalkBuilder;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::cache::GINPEE_DIR;
+use crate::filetype;
+use crate::formatter::human_size;
+use crate::mode::Modes;
+use crate::writer::SNAPSHOT_MARKER;
+
+/// Настройки обхода для [`collect`].
+#[derive(Debug, Clone)]
+pub struct CollectOptions {
+    /// Имя файла игнорирования (`.gpskip`)
+    pub ignore_file: String,
+    /// Паттерны исключения (`[files] exclude`)
+    pub exclude: Vec<String>,
+    /// Файлы, которые никогда не попадают в результат (например, сам `project.md`)
+    pub skip_paths: Vec<PathBuf>,
+    /// Брать все текстовые файлы, игнорируя паттерны (как при пустом `include`)
+    pub all: bool,
+    /// Считать ошибкой паттерн `include`, под который не подошёл ни один файл
+    pub strict: bool,
+    /// Семантика паттернов
+    pub matching: MatchOptions,
+    /// Что делать со скрытыми файлами и директориями
+    pub hidden: Hidden,
+    /// Заходить в директории по символическим ссылкам (циклы обнаруживаются и пропускаются)
+    pub follow_symlinks: bool,
+    /// Ограничения обхода
+    pub limits: Limits,
+    /// Число потоков обхода: `1` — последовательно, `0` — по числу ядер
+    pub jobs: usize,
+    /// Режимы встраивания: `max_total_bytes` считает то, что попадёт в результат, а не весь файл
+    pub modes: Option<Arc<Modes>>,
+}
+
+/// Ограничения, при превышении которых обход прерывается с ошибкой.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Максимальная глубина обхода (глубже директории просто не просматриваются)
+    pub max_depth: Option<usize>,
+    /// Максимальное число отобранных файлов
+    pub max_files: Option<usize>,
+    /// Максимальный суммарный размер отобранных файлов в байтах
+    pub max_total_bytes: Option<u64>,
+}
+
+impl Default for CollectOptions {
+    fn default() -> Self {
+        Self {
+            ignore_file: ".gpskip".to_string(),
+            exclude: Vec::new(),
+            skip_paths: Vec::new(),
+            all: false,
+            strict: false,
+            matching: MatchOptions::default(),
+            hidden: Hidden::default(),
+            follow_symlinks: false,
+            limits: Limits::default(),
+            jobs: 0,
+            modes: None,
+        }
+    }
+}
+
+/// Результат обхода: найденные файлы и предупреждения для пользователя.
+#[derive(Debug, Default)]
+pub struct Collection {
+    pub files: Vec<PathBuf>,
+    pub warnings: Vec<String>,
+    /// Сколько файлов подошло под каждый паттерн `include` (до применения `exclude`)
+    pub pattern_matches: Vec<(String, usize)>,
+    /// Файлы из `files`, до которых обход дошёл через символическую ссылку
+    pub links: BTreeMap<PathBuf, Link>,
+}
+
+/// Файл, найденный через символическую ссылку.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Что показать в дереве после `->`: цель ссылки или путь уже встроенной копии
+    pub target: PathBuf,
+    /// Встраивать ли содержимое: одна и та же цель встраивается только один раз
+    pub embed: bool,
+}
 
 pub fn collect_files(
     base_path: &Path,
     include_patterns: &[String],
     ignore_file: &str,
 ) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let options = CollectOptions {
+        ignore_file: ignore_file.to_string(),
+        ..CollectOptions::default()
+    };
+    Ok(collect(base_path, include_patterns, &options)?.files)
+}
+
+pub fn collect(
+    base_path: &Path,
+    include_patterns: &[String],
+    options: &CollectOptions,
+) -> Result<Collection, Box<dyn std::error::Error>> {
+    let walker = walker(base_path, options);
+
+    let patterns = PatternSet::with_options(include_patterns, options.matching)?;
+    let exclude = PatternSet::with_options(&options.exclude, options.matching)?;
+
+    let excluded: Vec<PathBuf> = options
+        .skip_paths
+        .iter()
+        .map(|path| normalize(&base_path.join(path)))
+        .collect();
+
+    let all_files = options.all || patterns.is_empty();
+    let filter = Filter {
+        base_path,
+        patterns: &patterns,
+        exclude: &exclude,
+        excluded: &excluded,
+        all_files,
+        hidden: options.hidden,
+        modes: options.modes.as_deref(),
+        max_depth: options.limits.max_depth,
+    };
+    let mut state = State {
+        counts: vec![0; include_patterns.len()],
+        ..State::default()
+    };
+
+    if options.jobs == 1 {
+        for result in walker.build() {
+            state.record(filter.check(result), &options.limits);
+            if state.error.is_some() {
+                break;
+            }
+        }
+    } else {
+        let shared = Mutex::new(state);
+        walker.build_parallel().run(|| {
+            Box::new(|result| {
+                // Чтение и сопоставление идут параллельно, под замком только учёт результата
+                let verdict = filter.check(result);
+                let mut state = shared.lock().unwrap();
+                state.record(verdict, &options.limits);
+                if state.error.is_some() { WalkState::Quit } else { WalkState::Continue }
+            })
+        });
+        state = shared.into_inner().unwrap();
+    }
+    if let Some(err) = state.error {
+        return Err(err.into());
+    }
+
+    // Порядок не должен зависеть от числа потоков
+    let mut collection = state.collection;
+    let counts = state.counts;
+    if let Some(max) = options.limits.max_depth
+        && !state.pruned.is_empty()
+    {
+        collection.warnings.push(depth_warning(max, state.pruned));
+    }
+    collection.files.sort();
+    collection.warnings.sort();
+
+    collection.links = resolve_links(base_path, &collection.files);
+
+    if !all_files {
+        // Отрицания (`!pattern`) только сужают выборку, их «пустота» — не ошибка
+        let unmatched: Vec<&String> = include_patterns
+            .iter()
+            .zip(&counts)
+            .filter(|(pattern, count)| **count == 0 && !pattern.starts_with('!'))
+            .map(|(pattern, _)| pattern)
+            .collect();
+        if options.strict && !unmatched.is_empty() {
+            let list: Vec<String> = unmatched.iter().map(|p| format!("`{}`", p)).collect();
+            return Err(format!("include patterns matched no files: {}", list.join(", ")).into());
+        }
+        for pattern in unmatched {
+            collection.warnings.push(format!("include pattern `{}` matched no files", pattern));
+        }
+        collection.pattern_matches = include_patterns.iter().cloned().zip(counts).collect();
+    }
+
+    Ok(collection)
+}
+
+/// Предупреждение о директориях, в которые обход не зашёл из-за `max_depth`.
+fn depth_warning(max_depth: usize, mut pruned: Vec<PathBuf>) -> String {
+    const SHOWN: usize = 5;
+    pruned.sort();
+    let mut names: Vec<String> = pruned.iter().take(SHOWN).map(|dir| format!("{}/", dir.display())).collect();
+    if pruned.len() > SHOWN {
+        names.push(format!("and {} more", pruned.len() - SHOWN));
+    }
+    format!(
+        "max_depth {} reached: contents of {} not walked; raise the limit to include them",
+        max_depth,
+        names.join(", ")
+    )
+}
+
+/// Обходчик с настройками из `options`: `.gpskip`, скрытые файлы, ссылки, глубина, потоки.
+fn walker(base_path: &Path, options: &CollectOptions) -> WalkBuilder {
     let mut walker = WalkBuilder::new(base_path);
-    walker.add_custom_ignore_filename(ignore_file);
+    walker.add_custom_ignore_filename(&options.ignore_file);
     walker.git_ignore(false);
     walker.git_global(false);
     walker.git_exclude(false);
-    let walker = walker.build();
+    walker.hidden(options.hidden == Hidden::Skip);
+    walker.follow_links(options.follow_symlinks);
+    walker.max_depth(options.limits.max_depth);
+    if options.hidden != Hidden::Skip {
+        // Служебные данные git и самого ginpee не нужны ни при какой политике
+        walker.filter_entry(|entry| entry.file_name() != ".git" && entry.file_name() != GINPEE_DIR);
+    }
+    walker.threads(options.jobs);
+    walker
+}
 
-    let patterns: Result<Vec<_>, _> = include_patterns.iter()
-        .map(|s| Pattern::new(s))
+/// Все файлы, которые видит обход (после `.gpskip`), без паттернов и эвристик, кроме `skip_paths`.
+///
+/// Нужен для дерева «для контекста»: в нём видно, что ещё есть в проекте.
+pub fn walk_all(base_path: &Path, options: &CollectOptions) -> Vec<PathBuf> {
+    let excluded: Vec<PathBuf> = options
+        .skip_paths
+        .iter()
+        .map(|path| normalize(&base_path.join(path)))
         .collect();
-
-    let patterns = patterns?;
-
-    let files: Vec<_> = walker
+    let mut files: Vec<PathBuf> = walker(base_path, options)
+        .build()
         .filter_map(|result| result.ok())
         .filter(|entry| entry.path().is_file())
-        .filter(|entry| {
-            let relative_path = entry.path().strip_prefix(base_path).unwrap();
-            
-            patterns.iter().any(|p| {
-                // Сначала проверяем полный путь
-                if p.matches_path(relative_path) {
-                    return true;
+        .map(DirEntry::into_path)
+        .filter(|path| !excluded.contains(&normalize(path)))
+        // В режиме `explicit` скрытое показывается, только если его назвал паттерн (тогда оно и так отобрано)
+        .filter(|path| {
+            options.hidden != Hidden::Explicit
+                || path.strip_prefix(base_path).is_ok_and(|rel| names_hidden_parts("", rel))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Неизменяемые данные для решения по отдельному файлу; разделяются между потоками обхода.
+struct Filter<'a> {
+    base_path: &'a Path,
+    patterns: &'a PatternSet,
+    exclude: &'a PatternSet,
+    excluded: &'a [PathBuf],
+    all_files: bool,
+    hidden: Hidden,
+    modes: Option<&'a Modes>,
+    max_depth: Option<usize>,
+}
+
+/// Решение по одной записи обхода.
+struct Verdict {
+    /// Какие паттерны `include` совпали (для подсчёта, даже если файл потом отброшен)
+    hits: Vec<usize>,
+    outcome: Outcome,
+}
+
+enum Outcome {
+    Skip,
+    Warn(String),
+    Take { path: PathBuf, relative_path: PathBuf, size: u64 },
+    /// Непустая директория на границе `max_depth`: её содержимое не просматривалось
+    Pruned(PathBuf),
+}
+
+impl Verdict {
+    fn skip(hits: Vec<usize>) -> Self {
+        Self { hits, outcome: Outcome::Skip }
+    }
+}
+
+impl Filter<'_> {
+    fn check(&self, result: Result<DirEntry, ignore::Error>) -> Verdict {
+        let entry = match result {
+            Ok(entry) => entry,
+            Err(err) => {
+                let Some((child, ancestor)) = symlink_loop(&err) else {
+                    return Verdict::skip(Vec::new());
+                };
+                let warning = format!(
+                    "skipping {}: symlink loop back to {}",
+                    child.strip_prefix(self.base_path).unwrap_or(child).display(),
+                    ancestor.strip_prefix(self.base_path).unwrap_or(ancestor).display()
+                );
+                return Verdict { hits: Vec::new(), outcome: Outcome::Warn(warning) };
+            }
+        };
+        if !entry.path().is_file() {
+            let pruned = self.max_depth.is_some_and(|max| max > 0 && entry.depth() == max)
+                && fs::read_dir(entry.path()).is_ok_and(|mut children| children.next().is_some());
+            if pruned && let Ok(relative_path) = entry.path().strip_prefix(self.base_path) {
+                return Verdict { hits: Vec::new(), outcome: Outcome::Pruned(relative_path.to_path_buf()) };
+            }
+            return Verdict::skip(Vec::new());
+        }
+        let relative_path = entry.path().strip_prefix(self.base_path).unwrap();
+
+        // Без паттернов берём все текстовые файлы, кроме бинарных, сгенерированных и lock-файлов
+        let (hits, pattern) = if self.all_files {
+            if filetype::classify(entry.path()).is_some() {
+                return Verdict::skip(Vec::new());
+            }
+            // Без паттернов скрытое ничем не названо явно
+            if self.hidden == Hidden::Explicit && !names_hidden_parts("", relative_path) {
+                return Verdict::skip(Vec::new());
+            }
+            (Vec::new(), "*")
+        } else {
+            let hits = self.patterns.matching(relative_path);
+            let Some(index) = self.patterns.decisive(&hits) else {
+                return Verdict::skip(hits);
+            };
+            // Скрытый путь должен быть назван явно хотя бы одним из совпавших паттернов
+            if self.hidden == Hidden::Explicit
+                && !hits.iter().any(|&hit| names_hidden_parts(self.patterns.as_str(hit), relative_path))
+            {
+                return Verdict::skip(hits);
+            }
+            let pattern = self.patterns.as_str(index);
+            (hits, pattern)
+        };
+
+        if self.exclude.find(relative_path).is_some() {
+            return Verdict::skip(hits);
+        }
+
+        if !self.excluded.is_empty() && self.excluded.contains(&normalize(entry.path())) {
+            // Без паттернов пропуск ожидаем; названный паттерном файл не должен пропасть молча
+            if self.all_files {
+                return Verdict::skip(hits);
+            }
+            let warning = format!(
+                "skipping {}: it is the output of this run or its backup (matched by `{}`)",
+                relative_path.display(),
+                pattern
+            );
+            return Verdict { hits, outcome: Outcome::Warn(warning) };
+        }
+
+        // Старый снимок, лежащий под другим именем, не должен попасть в новый
+        if is_snapshot(entry.path()) {
+            let warning = format!(
+                "skipping {}: it is a previously generated ginpee snapshot (matched by `{}`)",
+                relative_path.display(),
+                pattern
+            );
+            return Verdict { hits, outcome: Outcome::Warn(warning) };
+        }
+
+        let mut size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if let Some(modes) = self.modes {
+            size = modes.for_path(relative_path).embedded_size(entry.path(), size);
+        }
+        let relative_path = relative_path.to_path_buf();
+        Verdict { hits, outcome: Outcome::Take { path: entry.into_path(), relative_path, size } }
+    }
+}
+
+/// Накопленный результат обхода.
+#[derive(Default)]
+struct State {
+    collection: Collection,
+    counts: Vec<usize>,
+    usage: Usage,
+    /// Директории, глубже которых обход не пошёл из-за `max_depth`
+    pruned: Vec<PathBuf>,
+    /// Ошибка хранится строкой: `Box<dyn Error>` нельзя передавать между потоками
+    error: Option<String>,
+}
+
+impl State {
+    fn record(&mut self, verdict: Verdict, limits: &Limits) {
+        for hit in verdict.hits {
+            self.counts[hit] += 1;
+        }
+        match verdict.outcome {
+            Outcome::Skip => {}
+            Outcome::Warn(warning) => self.collection.warnings.push(warning),
+            Outcome::Pruned(dir) => self.pruned.push(dir),
+            Outcome::Take { path, relative_path, size } => {
+                if self.error.is_some() {
+                    return;
+                }
+                self.usage.add(&relative_path, size);
+                if let Err(err) = self.usage.check(limits) {
+                    self.error = Some(err.to_string());
+                    return;
                 }
-                
-                // Для паттернов без wildcards проверяем basename
-                let pattern_str = p.as_str();
-                if !pattern_str.contains('*') && !pattern_str.contains('?') && !pattern_str.contains('[') {
-                    if let Some(file_name) = entry.path().file_name() {
-                        return file_name == pattern_str;
+                self.collection.files.push(path);
+            }
+        }
+    }
+}
+
+/// Сколько уже отобрано: всего и по директориям (для сообщения об ошибке).
+#[derive(Debug, Default)]
+struct Usage {
+    files: usize,
+    bytes: u64,
+    dirs: BTreeMap<PathBuf, (usize, u64)>,
+}
+
+impl Usage {
+    /// Сколько уровней пути учитывать при группировке по директориям.
+    const DIR_DEPTH: usize = 2;
+    /// Сколько директорий показывать в ошибке.
+    const TOP_DIRS: usize = 5;
+
+    fn add(&mut self, relative_path: &Path, size: u64) {
+        self.files += 1;
+        self.bytes += size;
+        let dir: PathBuf = relative_path
+            .parent()
+            .map(|parent| parent.components().take(Self::DIR_DEPTH).collect())
+            .unwrap_or_default();
+        let entry = self.dirs.entry(dir).or_default();
+        entry.0 += 1;
+        entry.1 += size;
+    }
+
+    fn check(&self, limits: &Limits) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(max) = limits.max_files
+            && self.files > max
+        {
+            return Err(self.exceeded(format!("more than {} files selected (max_files)", max), |&(files, _)| files as u64));
+        }
+        if let Some(max) = limits.max_total_bytes
+            && self.bytes > max
+        {
+            return Err(self.exceeded(
+                format!("selected files exceed {} (max_total_bytes)", human_size(max)),
+                |&(_, bytes)| bytes,
+            ));
+        }
+        Ok(())
+    }
+
+    fn exceeded(&self, reason: String, weight: fn(&(usize, u64)) -> u64) -> Box<dyn std::error::Error> {
+        let mut dirs: Vec<_> = self.dirs.iter().collect();
+        dirs.sort_by_key(|(_, usage)| std::cmp::Reverse(weight(usage)));
+        let mut message = format!("walk limit exceeded: {}; biggest directories so far:", reason);
+        for (dir, (files, bytes)) in dirs.into_iter().take(Self::TOP_DIRS) {
+            let name = if dir.as_os_str().is_empty() { ".".to_string() } else { format!("{}/", dir.display()) };
+            message.push_str(&format!("\n  {} ({} files, {})", name, files, human_size(*bytes)));
+        }
+        message.push_str("\nnarrow the include patterns, add excludes or raise the limit");
+        message.into()
+    }
+}
+
+/// Политика для скрытых путей (имя начинается с `.`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hidden {
+    /// Не заходить в скрытые директории и не брать скрытые файлы
+    #[default]
+    Skip,
+    /// Обрабатывать как обычные
+    Include,
+    /// Брать, только если совпавший паттерн называет скрытые части пути явно
+    /// (`.github/workflows/*.yml`, `.envrc`), но не `*.yml`
+    Explicit,
+}
+
+/// Семантика паттернов `include` / `exclude`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Matching {
+    /// Исходный режим: `glob::Pattern`, `*` пересекает `/`, имя без wildcards ищется по basename
+    #[default]
+    Glob,
+    /// Как в `.gitignore`: `/` в начале привязывает к корню, `**` — рекурсия,
+    /// `{a,b}` — альтернативы, `!` — отрицание, последний совпавший паттерн решает
+    Gitignore,
+}
+
+/// Как компилировать паттерны.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MatchOptions {
+    pub matching: Matching,
+    /// Только для `gitignore`: сравнение без учёта регистра
+    pub case_insensitive: bool,
+}
+
+/// Набор include/exclude паттернов; тот же сопоставитель используют `ginpee add` / `remove`.
+#[derive(Debug, Clone)]
+pub struct PatternSet {
+    sources: Vec<String>,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    Glob(Vec<Pattern>),
+    Gitignore {
+        set: GlobSet,
+        /// Какому паттерну принадлежит каждый glob из `set`
+        owners: Vec<usize>,
+        negated: Vec<bool>,
+    },
+}
+
+impl Default for PatternSet {
+    fn default() -> Self {
+        Self { sources: Vec::new(), kind: Kind::Glob(Vec::new()) }
+    }
+}
+
+impl PatternSet {
+    pub fn new(patterns: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_options(patterns, MatchOptions::default())
+    }
+
+    pub fn with_options(
+        patterns: &[String],
+        options: MatchOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let kind = match options.matching {
+            Matching::Glob => Kind::Glob(
+                patterns
+                    .iter()
+                    .map(|s| Pattern::new(s).map_err(|e| format!("invalid pattern `{}`: {}", s, e)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Matching::Gitignore => {
+                let mut builder = GlobSetBuilder::new();
+                let mut owners = Vec::new();
+                let mut negated = Vec::new();
+                for (index, source) in patterns.iter().enumerate() {
+                    let (globs, is_negated) = gitignore_globs(source);
+                    negated.push(is_negated);
+                    for glob in globs {
+                        let glob = GlobBuilder::new(&glob)
+                            .literal_separator(true)
+                            .case_insensitive(options.case_insensitive)
+                            .build()
+                            .map_err(|e| format!("invalid pattern `{}`: {}", source, e))?;
+                        builder.add(glob);
+                        owners.push(index);
                     }
                 }
-                
-                false
-            })
+                Kind::Gitignore { set: builder.build()?, owners, negated }
+            }
+        };
+        Ok(Self { sources: patterns.to_vec(), kind })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Паттерн, по которому путь (относительно корня проекта) попадает в набор.
+    pub fn find(&self, relative_path: &Path) -> Option<&str> {
+        let hits = self.matching(relative_path);
+        self.decisive(&hits).map(|index| self.as_str(index))
+    }
+
+    /// Индексы всех паттернов (включая отрицательные), под которые подходит путь.
+    pub fn matching(&self, relative_path: &Path) -> Vec<usize> {
+        match &self.kind {
+            Kind::Glob(patterns) => (0..patterns.len())
+                .filter(|&i| matches(&patterns[i], relative_path))
+                .collect(),
+            Kind::Gitignore { set, owners, .. } => {
+                let mut hits: Vec<usize> = set
+                    .matches(relative_path)
+                    .into_iter()
+                    .map(|glob| owners[glob])
+                    .collect();
+                hits.dedup();
+                hits
+            }
+        }
+    }
+
+    /// Паттерн, который решает судьбу пути: первый совпавший для `glob`, последний —
+    /// для `gitignore`; `None`, если совпадений нет или последнее из них — отрицание.
+    pub fn decisive(&self, hits: &[usize]) -> Option<usize> {
+        match &self.kind {
+            Kind::Glob(_) => hits.first().copied(),
+            Kind::Gitignore { negated, .. } => hits.last().copied().filter(|&i| !negated[i]),
+        }
+    }
+
+    pub fn as_str(&self, index: usize) -> &str {
+        &self.sources[index]
+    }
+}
+
+/// Переводит gitignore-паттерн в globs для `globset`; второй элемент — признак `!`.
+fn gitignore_globs(pattern: &str) -> (Vec<String>, bool) {
+    let (pattern, negated) = match pattern.strip_prefix('!') {
+        Some(rest) => (rest, true),
+        None => (pattern.strip_prefix('\\').unwrap_or(pattern), false),
+    };
+
+    // `dir/` — только содержимое директории
+    let (pattern, dir_only) = match pattern.strip_suffix('/') {
+        Some(rest) => (rest, true),
+        None => (pattern, false),
+    };
+    // Паттерн со `/` в начале или середине привязан к корню, иначе ищется на любой глубине
+    let glob = match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if pattern.contains('/') || pattern.starts_with("**") => pattern.to_string(),
+        None => format!("**/{}", pattern),
+    };
+
+    let globs = if dir_only {
+        vec![format!("{}/**", glob)]
+    } else {
+        // Совпадение с директорией включает всё её содержимое
+        vec![glob.clone(), format!("{}/**", glob)]
+    };
+    (globs, negated)
+}
+
+fn matches(pattern: &Pattern, relative_path: &Path) -> bool {
+    // `./path` привязан к корню проекта: без совпадения по basename на любой глубине
+    if pattern.as_str().starts_with("./") {
+        return pattern.matches_path(&Path::new(".").join(relative_path));
+    }
+
+    // Сначала проверяем полный путь
+    if pattern.matches_path(relative_path) {
+        return true;
+    }
+
+    // Для паттернов без wildcards проверяем basename
+    let pattern_str = pattern.as_str();
+    if !pattern_str.contains('*') && !pattern_str.contains('?') && !pattern_str.contains('[')
+        && let Some(file_name) = relative_path.file_name()
+    {
+        return file_name == pattern_str;
+    }
+
+    false
+}
+
+/// Каждая скрытая часть пути (`.github`, `.envrc`) буквально встречается в паттерне.
+fn names_hidden_parts(pattern: &str, relative_path: &Path) -> bool {
+    relative_path
+        .components()
+        .filter_map(|c| c.as_os_str().to_str())
+        .filter(|name| name.starts_with('.'))
+        .all(|name| {
+            pattern
+                .split('/')
+                .any(|part| part == name || part.trim_start_matches('!') == name)
+        })
+}
+
+/// Помечает файлы, найденные через символические ссылки.
+///
+/// Содержимое встраивается один раз: если цель уже есть среди обычных файлов
+/// или её встроила более ранняя ссылка, в дереве остаётся только `путь -> цель`.
+fn resolve_links(base_path: &Path, files: &[PathBuf]) -> BTreeMap<PathBuf, Link> {
+    let base = normalize(base_path);
+    let resolved: Vec<(&PathBuf, PathBuf, bool)> = files
+        .iter()
+        .map(|path| {
+            let canonical = normalize(path);
+            let direct = path
+                .strip_prefix(base_path)
+                .is_ok_and(|relative| canonical == base.join(relative));
+            (path, canonical, direct)
         })
-        .map(|entry| entry.path().to_path_buf())
         .collect();
 
-    Ok(files)
+    let mut embedded: HashSet<&PathBuf> = resolved
+        .iter()
+        .filter(|(_, _, direct)| *direct)
+        .map(|(_, canonical, _)| canonical)
+        .collect();
+    let mut links = BTreeMap::new();
+    for (path, canonical, direct) in &resolved {
+        if *direct {
+            continue;
+        }
+        let target = match fs::read_link(path) {
+            Ok(target) => target,
+            Err(_) => canonical.strip_prefix(&base).unwrap_or(canonical).to_path_buf(),
+        };
+        let embed = embedded.insert(canonical);
+        links.insert(path.to_path_buf(), Link { target, embed });
+    }
+    links
+}
+
+/// Цикл символических ссылок из ошибки обхода (ошибка может быть обёрнута в путь / глубину).
+fn symlink_loop(err: &ignore::Error) -> Option<(&Path, &Path)> {
+    match err {
+        ignore::Error::Loop { ancestor, child } => Some((child, ancestor)),
+        ignore::Error::WithPath { err, .. } | ignore::Error::WithDepth { err, .. } => symlink_loop(err),
+        _ => None,
+    }
+}
+
+/// Заголовок дерева во встроенном формате.
+const STRUCTURE_HEADER: &str = "# Project Structure\n\n```\n";
+
+/// Файл — снимок ginpee: начинается с маркера (шаблон с `{{ marker }}`) или содержит маркер
+/// прямо перед деревом (встроенный формат, маркер идёт после `[top]`).
+pub fn is_snapshot(path: &Path) -> bool {
+    let Some(head) = read_head(path, 8 * 1024) else {
+        return false;
+    };
+    head.starts_with(SNAPSHOT_MARKER) || head.contains(&format!("{}\n{}", SNAPSHOT_MARKER, STRUCTURE_HEADER))
+}
+
+/// Снимок версии до маркера: дерево в блоке кода, за которым сразу идут разделы `## File:`.
+///
+/// Одного заголовка мало — так же может выглядеть и написанный руками файл, поэтому нужен весь макет.
+/// Проверяется только для самого результата (перезапись без `--force`), в обходе такие файлы не пропускаются.
+pub fn is_legacy_snapshot(path: &Path) -> bool {
+    // Дерево большого проекта может не поместиться в 8 KiB
+    let Some(head) = read_head(path, 1024 * 1024) else {
+        return false;
+    };
+    let tree = if head.starts_with(STRUCTURE_HEADER) {
+        STRUCTURE_HEADER.len()
+    } else if let Some(start) = head.find(&format!("\n{}", STRUCTURE_HEADER)) {
+        start + 1 + STRUCTURE_HEADER.len()
+    } else {
+        return false;
+    };
+    let rest = &head[tree..];
+    rest.find("\n```\n")
+        .is_some_and(|end| rest[end + "\n```\n".len()..].starts_with("\n## File: `"))
+}
+
+fn read_head(path: &Path, limit: u64) -> Option<String> {
+    let mut head = Vec::new();
+    File::open(path).ok()?.take(limit).read_to_end(&mut head).ok()?;
+    Some(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// Абсолютный путь без `..` и симлинков; для ещё не созданного файла — через родителя.
+fn normalize(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    match (path.parent().and_then(|p| p.canonicalize().ok()), path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
 }
 
 #[cfg(test)]
@@ -97,4 +808,215 @@ mod tests {
         assert_eq!(files.len(), 1);
         assert!(files[0].ends_with("Cargo.toml"));
     }
+
+    #[test]
+    fn test_collect_all_text_files_when_include_is_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("main.rs"), "fn main() {}").unwrap();
+        fs::write(temp_dir.path().join("Cargo.lock"), "version = 4").unwrap();
+        fs::write(temp_dir.path().join("icon.ico"), [0u8, 0, 1, 0]).unwrap();
+
+        let files = collect_files(temp_dir.path(), &[], ".gpskip").unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].ends_with("main.rs"));
+
+        let options = CollectOptions { all: true, ..CollectOptions::default() };
+        let collection = collect(temp_dir.path(), &["*.md".to_string()], &options).unwrap();
+        assert_eq!(collection.files.len(), 1);
+    }
+
+    #[test]
+    fn test_collect_with_exclude_patterns() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("src/generated")).unwrap();
+        fs::write(temp_dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(temp_dir.path().join("src/generated/api.rs"), "// api").unwrap();
+
+        let options = CollectOptions {
+            exclude: vec!["src/generated/*".to_string()],
+            ..CollectOptions::default()
+        };
+        let collection = collect(temp_dir.path(), &["*.rs".to_string()], &options).unwrap();
+        assert_eq!(collection.files.len(), 1);
+        assert!(collection.files[0].ends_with("main.rs"));
+    }
+
+    #[test]
+    fn test_collect_reports_unmatched_patterns() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Makefile"), "all:").unwrap();
+        let patterns = vec!["Makefile".to_string(), "Makefle".to_string()];
+
+        let collection = collect(temp_dir.path(), &patterns, &CollectOptions::default()).unwrap();
+        assert_eq!(collection.files.len(), 1);
+        assert_eq!(collection.warnings, vec!["include pattern `Makefle` matched no files"]);
+        assert_eq!(collection.pattern_matches[0], ("Makefile".to_string(), 1));
+
+        let strict = CollectOptions { strict: true, ..CollectOptions::default() };
+        let error = collect(temp_dir.path(), &patterns, &strict).unwrap_err();
+        assert!(error.to_string().contains("`Makefle`"));
+    }
+
+    #[test]
+    fn test_gitignore_matching() {
+        let temp_dir = TempDir::new().unwrap();
+        for path in ["Cargo.toml", "src/main.rs", "src/bin/tool.rs", "src/bin/Cargo.toml", "docs/A.MD", "src/gen/api.rs"] {
+            let path = temp_dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+        let relative = |collection: Collection| -> Vec<String> {
+            let mut files: Vec<String> = collection
+                .files
+                .iter()
+                .map(|f| f.strip_prefix(temp_dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect();
+            files.sort();
+            files
+        };
+        let options = CollectOptions {
+            matching: MatchOptions { matching: Matching::Gitignore, case_insensitive: true },
+            ..CollectOptions::default()
+        };
+        let patterns: Vec<String> = ["/Cargo.toml", "src/*.rs", "src/**/*.{rs,toml}", "!src/gen/", "*.md"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let files = relative(collect(temp_dir.path(), &patterns, &options).unwrap());
+        assert_eq!(
+            files,
+            vec!["Cargo.toml", "docs/A.MD", "src/bin/Cargo.toml", "src/bin/tool.rs", "src/main.rs"]
+        );
+
+        // `*` не пересекает `/`, а `/Cargo.toml` привязан к корню
+        let patterns = vec!["/*.rs".to_string(), "/Cargo.toml".to_string()];
+        let collection = collect(temp_dir.path(), &patterns, &options).unwrap();
+        assert_eq!(relative(collection), vec!["Cargo.toml"]);
+    }
+
+    #[test]
+    fn test_hidden_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join(".github/workflows")).unwrap();
+        fs::create_dir_all(temp_dir.path().join(".git")).unwrap();
+        fs::write(temp_dir.path().join(".github/workflows/ci.yml"), "on: push").unwrap();
+        fs::write(temp_dir.path().join(".git/config.yml"), "[core]").unwrap();
+        fs::write(temp_dir.path().join("app.yml"), "app: 1").unwrap();
+        let count = |hidden: Hidden, patterns: &[&str]| {
+            let patterns: Vec<String> = patterns.iter().map(|s| s.to_string()).collect();
+            let options = CollectOptions { hidden, ..CollectOptions::default() };
+            collect(temp_dir.path(), &patterns, &options).unwrap().files.len()
+        };
+
+        assert_eq!(count(Hidden::Skip, &["**/*.yml"]), 1);
+        assert_eq!(count(Hidden::Include, &["**/*.yml"]), 2);
+        assert_eq!(count(Hidden::Explicit, &["**/*.yml"]), 1);
+        assert_eq!(count(Hidden::Explicit, &["**/*.yml", ".github/**/*.yml"]), 2);
+        assert_eq!(count(Hidden::Explicit, &[".github/**/*.yml", "*.yml"]), 2);
+    }
+
+    #[test]
+    fn test_parallel_walk_matches_sequential() {
+        let temp_dir = TempDir::new().unwrap();
+        for dir in 0..8 {
+            fs::create_dir_all(temp_dir.path().join(format!("src/m{}", dir))).unwrap();
+            for file in 0..16 {
+                fs::write(temp_dir.path().join(format!("src/m{}/f{}.rs", dir, file)), "// f").unwrap();
+            }
+        }
+        let patterns = vec!["src/**/*.rs".to_string(), "*.md".to_string()];
+        let run = |jobs: usize| {
+            let options = CollectOptions { jobs, ..CollectOptions::default() };
+            collect(temp_dir.path(), &patterns, &options).unwrap()
+        };
+
+        let sequential = run(1);
+        let parallel = run(4);
+        assert_eq!(sequential.files.len(), 128);
+        assert_eq!(parallel.files, sequential.files);
+        assert_eq!(parallel.pattern_matches, sequential.pattern_matches);
+        assert_eq!(parallel.warnings, sequential.warnings);
+    }
+
+    #[test]
+    fn test_limits() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("vendor/big/deep")).unwrap();
+        fs::write(temp_dir.path().join("main.rs"), "fn main() {}").unwrap();
+        fs::write(temp_dir.path().join("vendor/big/a.rs"), "x".repeat(2000)).unwrap();
+        fs::write(temp_dir.path().join("vendor/big/deep/b.rs"), "x".repeat(2000)).unwrap();
+        let patterns = vec!["*.rs".to_string()];
+        let run = |limits: Limits| {
+            let options = CollectOptions { limits, ..CollectOptions::default() };
+            collect(temp_dir.path(), &patterns, &options)
+        };
+
+        let shallow = run(Limits { max_depth: Some(1), ..Limits::default() }).unwrap();
+        assert_eq!(shallow.files.len(), 1);
+        assert_eq!(shallow.warnings.len(), 1);
+        assert!(shallow.warnings[0].contains("max_depth 1 reached: contents of vendor/ not walked"), "{:?}", shallow.warnings);
+        assert!(run(Limits { max_depth: Some(4), ..Limits::default() }).unwrap().warnings.is_empty());
+
+        let err = run(Limits { max_files: Some(2), ..Limits::default() }).unwrap_err().to_string();
+        assert!(err.contains("max_files"), "{}", err);
+
+        let err = run(Limits { max_total_bytes: Some(3000), ..Limits::default() }).unwrap_err().to_string();
+        assert!(err.contains("vendor/big/ (2 files, 3.9 KiB)"), "{}", err);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_embedded_once_and_loops_skipped() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("shared")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("shared/util.rs"), "// util").unwrap();
+        symlink("../shared/util.rs", root.join("src/util.rs")).unwrap();
+        symlink("../shared", root.join("src/shared")).unwrap();
+        symlink("..", root.join("shared/up")).unwrap();
+
+        let patterns = vec!["src/**/*.rs".to_string()];
+        let options = CollectOptions { follow_symlinks: true, ..CollectOptions::default() };
+        let collection = collect(root, &patterns, &options).unwrap();
+
+        assert_eq!(collection.files.len(), 2);
+        let embedded: Vec<_> = collection.links.values().filter(|link| link.embed).collect();
+        assert_eq!(embedded.len(), 1);
+        assert_eq!(collection.links[&root.join("src/util.rs")].target, PathBuf::from("../shared/util.rs"));
+        assert!(collection.warnings.iter().any(|w| w.contains("symlink loop")));
+
+        let collection = collect(root, &patterns, &CollectOptions::default()).unwrap();
+        assert_eq!(collection.files, vec![root.join("src/util.rs")]);
+    }
+
+    #[test]
+    fn test_collect_skips_output_and_old_snapshots() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("README.md"), "# Readme").unwrap();
+        fs::write(temp_dir.path().join("project.md"), "# Project Structure").unwrap();
+        fs::write(
+            temp_dir.path().join("old-snapshot.md"),
+            format!("Intro\n{}\n# Project Structure\n\n```\nREADME.md\n```\n", SNAPSHOT_MARKER),
+        )
+        .unwrap();
+        // Свой файл с тем же заголовком — не снимок
+        fs::write(temp_dir.path().join("STRUCTURE.md"), "# Project Structure\n\n```\nsrc/\n```\n\nNotes\n").unwrap();
+
+        let options = CollectOptions {
+            skip_paths: vec![PathBuf::from("project.md")],
+            ..CollectOptions::default()
+        };
+        let collection = collect(temp_dir.path(), &["*.md".to_string()], &options).unwrap();
+
+        assert_eq!(collection.files.len(), 2);
+        assert!(collection.files[0].ends_with("README.md"));
+        assert!(collection.files[1].ends_with("STRUCTURE.md"));
+        assert_eq!(collection.warnings.len(), 2, "{:?}", collection.warnings);
+        assert!(collection.warnings[0].contains("old-snapshot.md"));
+        assert!(collection.warnings[1].contains("project.md: it is the output"));
+    }
 }
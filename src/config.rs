@@ -1,31 +1,492 @@
 //! Модуль загрузки и парсинга конфигурации `ginpee.toml`.
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[derive(Deserialize, Default, Debug)]
+use crate::collector::{Hidden, MatchOptions, Matching};
+use crate::mode::Rule;
+
+#[derive(Deserialize, Serialize, Default, Debug)]
 pub struct Config {
     pub top: Option<Content>,
     pub down: Option<Content>,
     pub files: Option<Files>,
+    pub output: Option<Output>,
+    /// Настройки обхода файловой системы
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub walk: Option<Walk>,
+    /// Настройки дерева в разделе `# Project Structure`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tree: Option<Tree>,
+    /// Именованные наборы паттернов, выбираются через `--profile`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Files>,
+    /// Пользовательские переменные для `${name}` и `{{name}}`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
 pub struct Files {
+    #[serde(default)]
     pub include: Vec<String>,
+    /// Паттерны файлов, которые исключаются даже при совпадении с `include`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Семантика паттернов; без ключа — исходный режим `glob` для совместимости
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matching: Option<Matching>,
+    /// Для `matching = "gitignore"`: сравнение без учёта регистра
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub case_insensitive: bool,
+    /// Режимы встраивания по паттернам; решает первое совпавшее правило
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modes: Vec<Rule>,
+}
+
+impl Files {
+    pub fn match_options(&self) -> MatchOptions {
+        MatchOptions {
+            matching: self.matching.unwrap_or_default(),
+            case_insensitive: self.case_insensitive,
+        }
+    }
 }
 
-#[derive(Deserialize, Debug)]
+impl Config {
+    /// Активный набор паттернов: `[profiles.NAME]` или `[files]`.
+    ///
+    /// Профиль без собственного `matching` или `modes` наследует их из `[files]`.
+    pub fn selection(&self, profile: Option<&str>) -> Result<Files, Box<dyn std::error::Error>> {
+        let files = self.files.clone().unwrap_or_default();
+        let Some(name) = profile else {
+            return Ok(files);
+        };
+        let mut selected = self
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("unknown profile `{}`", name))?;
+        if selected.matching.is_none() {
+            selected.matching = files.matching;
+            selected.case_insensitive |= files.case_insensitive;
+        }
+        if selected.modes.is_empty() {
+            selected.modes = files.modes;
+        }
+        Ok(selected)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
 pub struct Content {
+    #[serde(default)]
     pub text: String,
+    /// Файл или список файлов относительно директории конфига
+    #[serde(default, deserialize_with = "one_or_many", skip_serializing_if = "Vec::is_empty")]
+    pub file: Vec<PathBuf>,
+}
+
+impl Content {
+    /// Собирает итоговый текст: сначала `text`, затем содержимое файлов по порядку.
+    pub fn resolve(&self, base_dir: &Path) -> Result<String, Box<dyn std::error::Error>> {
+        let mut parts = Vec::new();
+        if !self.text.is_empty() {
+            parts.push(self.text.clone());
+        }
+        for file in &self.file {
+            let path = base_dir.join(file);
+            let content = fs::read_to_string(&path)
+                .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+            parts.push(content.trim_end_matches('\n').to_string());
+        }
+        Ok(parts.join("\n"))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Output {
+    /// Путь к результату (перекрывается `--output`)
+    pub path: Option<PathBuf>,
+    /// Путь к шаблону относительно директории конфига
+    pub template: Option<PathBuf>,
+    /// Сколько предыдущих снимков хранить (`project.md.bak`, `project.md.bak.1`, ...)
+    pub backups: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct Walk {
+    /// Политика для скрытых файлов и директорий
+    #[serde(default)]
+    pub hidden: Hidden,
+    /// Заходить в директории по символическим ссылкам
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// Максимальная глубина обхода
+    pub max_depth: Option<usize>,
+    /// Максимальное число отобранных файлов
+    pub max_files: Option<usize>,
+    /// Максимальный суммарный размер отобранных файлов в байтах
+    pub max_total_bytes: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct Tree {
+    /// Показывать в дереве все файлы проекта (после `.gpskip`), а не только отобранные
+    #[serde(default)]
+    pub full: bool,
+    /// Сколько элементов показывать в одной директории
+    pub max_entries: Option<usize>,
+    /// Директории, которые всегда сворачиваются
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collapse: Vec<String>,
+    /// Директории, которые всегда показываются целиком
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expand: Vec<String>,
+}
+
+/// Маркер в начале списка: заменить список базового конфига, а не дополнить его.
+pub const OVERRIDE_MARKER: &str = "!override";
+
+/// Пути в базовых конфигах, которые пересчитываются относительно наследника.
+const PATH_KEYS: &[(&str, &str)] = &[("top", "file"), ("down", "file"), ("output", "template")];
+
+/// Откуда взята конфигурация.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    File(PathBuf),
+    CargoToml(PathBuf),
+    Pyproject(PathBuf),
+    PackageJson(PathBuf),
+    Default,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::CargoToml(path) => write!(f, "{} [package.metadata.ginpee]", path.display()),
+            Source::Pyproject(path) => write!(f, "{} [tool.ginpee]", path.display()),
+            Source::PackageJson(path) => write!(f, "{} \"ginpee\"", path.display()),
+            Source::Default => write!(f, "built-in defaults"),
+        }
+    }
 }
 
+/// Конфиг по умолчанию, если `--config` не указан.
+pub const DEFAULT_CONFIG: &str = "ginpee.toml";
+
+/// Таблица ginpee в `Cargo.toml`, в порядке приоритета.
+const CARGO_KEYS: &[&[&str]] = &[&["package", "metadata", "ginpee"], &["workspace", "metadata", "ginpee"]];
+
+/// Таблица ginpee в `pyproject.toml`.
+const PYPROJECT_KEYS: &[&str] = &["tool", "ginpee"];
+
 pub fn load(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
-    let content = fs::read_to_string(path)?;
-    let config: Config = toml::from_str(&content)?;
+    let table = load_table(path, &mut Vec::new())?;
+    finish(table, path)
+}
+
+/// Ищет конфигурацию в порядке приоритета:
+///
+/// 1. `config_path` (по умолчанию `ginpee.toml`);
+/// 2. `[package.metadata.ginpee]` (или `[workspace.metadata.ginpee]`) в `Cargo.toml`;
+/// 3. `[tool.ginpee]` в `pyproject.toml`;
+/// 4. ключ `"ginpee"` в `package.json`;
+/// 5. встроенные значения по умолчанию.
+///
+/// Манифесты ищутся в директории `config_path`.
+pub fn discover(config_path: &Path) -> Result<(Config, Source), Box<dyn std::error::Error>> {
+    if config_path.exists() {
+        return Ok((load(config_path)?, Source::File(config_path.to_path_buf())));
+    }
+
+    let dir = base_dir(config_path);
+
+    let cargo = dir.join("Cargo.toml");
+    if let Some(table) = read_toml_section(&cargo, CARGO_KEYS)? {
+        return Ok((load_embedded(table, &cargo)?, Source::CargoToml(cargo)));
+    }
+
+    let pyproject = dir.join("pyproject.toml");
+    if let Some(table) = read_toml_section(&pyproject, &[PYPROJECT_KEYS])? {
+        return Ok((load_embedded(table, &pyproject)?, Source::Pyproject(pyproject)));
+    }
+
+    let package_json = dir.join("package.json");
+    if package_json.is_file() {
+        let content = fs::read_to_string(&package_json)?;
+        let json: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| format!("{}: {}", package_json.display(), e))?;
+        if let Some(section) = json.get("ginpee") {
+            let table = toml::Table::try_from(section)
+                .map_err(|e| format!("{}: invalid \"ginpee\" section: {}", package_json.display(), e))?;
+            return Ok((load_embedded(table, &package_json)?, Source::PackageJson(package_json)));
+        }
+    }
+
+    Ok((Config::default(), Source::Default))
+}
+
+/// Путь к конфигу из `--config` или [`DEFAULT_CONFIG`].
+pub fn path(explicit: Option<&Path>) -> &Path {
+    explicit.unwrap_or(Path::new(DEFAULT_CONFIG))
+}
+
+/// Конфигурация для `--config`: явно указанный файл обязан существовать,
+/// к манифестам и встроенным значениям [`discover`] переходит только без флага.
+pub fn resolve(explicit: Option<&Path>) -> Result<(Config, Source), Box<dyn std::error::Error>> {
+    match explicit {
+        Some(path) if !path.exists() => Err(format!("config file {} not found", path.display()).into()),
+        _ => discover(path(explicit)),
+    }
+}
+
+/// Куда `add`, `remove` и `pick` записывают изменения: файл, из которого взята конфигурация,
+/// и путь к таблице ginpee в нём (пустой для `ginpee.toml`).
+///
+/// Новый `ginpee.toml` рядом с манифестом перекрыл бы весь конфиг из манифеста, поэтому правится сам манифест;
+/// `package.json` не правится — форматирование JSON не сохранить.
+pub fn edit_location(
+    source: &Source,
+    explicit: Option<&Path>,
+) -> Result<(PathBuf, &'static [&'static str]), Box<dyn std::error::Error>> {
+    match source {
+        Source::File(file) => Ok((file.clone(), &[])),
+        Source::Default => Ok((path(explicit).to_path_buf(), &[])),
+        Source::CargoToml(cargo) => {
+            for keys in CARGO_KEYS {
+                if read_toml_section(cargo, &[keys])?.is_some() {
+                    return Ok((cargo.clone(), keys));
+                }
+            }
+            Err(format!("{}: [package.metadata.ginpee] not found", cargo.display()).into())
+        }
+        Source::Pyproject(pyproject) => Ok((pyproject.clone(), PYPROJECT_KEYS)),
+        Source::PackageJson(package_json) => Err(format!(
+            "the configuration comes from the \"ginpee\" key in {}; edit it there, ginpee does not rewrite JSON",
+            package_json.display()
+        )
+        .into()),
+    }
+}
+
+/// Полностью разрешённый конфиг в виде TOML (для `ginpee config show`).
+pub fn show(config_path: Option<&Path>, verbose: bool) -> Result<String, Box<dyn std::error::Error>> {
+    let (config, source) = resolve(config_path)?;
+    let mut output = String::new();
+    if verbose {
+        output.push_str(&format!("# source: {}\n", source));
+    }
+    output.push_str(&toml::to_string_pretty(&config)?);
+    Ok(output)
+}
+
+fn finish(mut table: toml::Table, path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+    crate::interpolate::interpolate_table(&mut table)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let config: Config = table.try_into().map_err(|e| format!("{}: {}", path.display(), e))?;
     Ok(config)
 }
 
+/// Конфиг внутри манифеста: `extends` разрешается относительно манифеста.
+fn load_embedded(table: toml::Table, manifest: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+    let mut stack = vec![fs::canonicalize(manifest)?];
+    let table = apply_extends(table, manifest, &mut stack)?;
+    finish(table, manifest)
+}
+
+/// Первая найденная секция по одному из путей ключей; `None`, если файла или секции нет.
+fn read_toml_section(
+    path: &Path,
+    key_paths: &[&[&str]],
+) -> Result<Option<toml::Table>, Box<dyn std::error::Error>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    let manifest: toml::Table =
+        toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    for keys in key_paths {
+        let mut current = Some(&manifest);
+        for key in *keys {
+            current = current.and_then(|t| t.get(*key)).and_then(|v| v.as_table());
+        }
+        if let Some(section) = current {
+            return Ok(Some(section.clone()));
+        }
+    }
+    Ok(None)
+}
+
+/// Читает конфиг и рекурсивно подмешивает базовые конфиги из `extends`.
+///
+/// Базовые конфиги применяются по порядку, наследник — последним. Таблицы
+/// сливаются по ключам, скаляры перекрываются, списки дописываются в конец,
+/// если только список наследника не начинается с `"!override"`.
+fn load_table(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+) -> Result<toml::Table, Box<dyn std::error::Error>> {
+    let canonical = fs::canonicalize(path)
+        .map_err(|e| format!("failed to read config {}: {}", path.display(), e))?;
+    if stack.contains(&canonical) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(format!("config inheritance cycle: {}", chain.join(" -> ")).into());
+    }
+
+    let content = fs::read_to_string(path)?;
+    let table: toml::Table =
+        toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    stack.push(canonical);
+    let merged = apply_extends(table, path, stack)?;
+    stack.pop();
+    Ok(merged)
+}
+
+/// Подмешивает базовые конфиги из `extends` под таблицу конфига, лежащего в `path`.
+fn apply_extends(
+    mut table: toml::Table,
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+) -> Result<toml::Table, Box<dyn std::error::Error>> {
+    let extends = match table.remove("extends") {
+        None => Vec::new(),
+        Some(toml::Value::String(base)) => vec![base],
+        Some(toml::Value::Array(bases)) => bases
+            .into_iter()
+            .map(|b| match b {
+                toml::Value::String(base) => Ok(base),
+                _ => Err(format!("{}: `extends` must contain paths", path.display())),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(format!("{}: `extends` must be a path or a list of paths", path.display()).into()),
+    };
+
+    let mut merged = toml::Table::new();
+    for base in extends {
+        let mut base_table = load_table(&base_dir(path).join(&base), stack)?;
+        if let Some(prefix) = Path::new(&base).parent() {
+            rebase_paths(&mut base_table, prefix);
+        }
+        merge_tables(&mut merged, base_table);
+    }
+
+    merge_tables(&mut merged, table);
+    Ok(merged)
+}
+
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(table)) => {
+                merge_tables(base_table, table);
+            }
+            (Some(toml::Value::Array(base_items)), toml::Value::Array(items))
+                if !is_override(&items) =>
+            {
+                base_items.extend(items);
+            }
+            (_, mut value) => {
+                strip_override_markers(&mut value);
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn is_override(items: &[toml::Value]) -> bool {
+    items.first().and_then(|v| v.as_str()) == Some(OVERRIDE_MARKER)
+}
+
+/// Убирает маркеры из значения, которое вставляется целиком, без слияния.
+fn strip_override_markers(value: &mut toml::Value) {
+    match value {
+        toml::Value::Array(items) => {
+            if is_override(items) {
+                items.remove(0);
+            }
+            items.iter_mut().for_each(strip_override_markers);
+        }
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, v)| strip_override_markers(v)),
+        _ => {}
+    }
+}
+
+fn rebase_paths(table: &mut toml::Table, prefix: &Path) {
+    if prefix.as_os_str().is_empty() {
+        return;
+    }
+    for (section, key) in PATH_KEYS {
+        let Some(toml::Value::Table(section)) = table.get_mut(*section) else {
+            continue;
+        };
+        match section.get_mut(*key) {
+            Some(toml::Value::String(path)) => *path = rebase(prefix, path),
+            Some(toml::Value::Array(paths)) => {
+                for item in paths.iter_mut() {
+                    if let toml::Value::String(path) = item
+                        && path != OVERRIDE_MARKER
+                    {
+                        *path = rebase(prefix, path);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn rebase(prefix: &Path, path: &str) -> String {
+    prefix.join(path).to_string_lossy().replace('\\', "/")
+}
+
+/// Текст из CLI: `@path` читает файл, `@@` экранирует ведущий `@`.
+pub fn resolve_cli_text(value: &str) -> Result<String, Box<dyn std::error::Error>> {
+    if let Some(literal) = value.strip_prefix("@@") {
+        return Ok(format!("@{}", literal));
+    }
+    match value.strip_prefix('@') {
+        Some(path) => Ok(fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path, e))?
+            .trim_end_matches('\n')
+            .to_string()),
+        None => Ok(value.to_string()),
+    }
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<PathBuf>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(PathBuf),
+        Many(Vec<PathBuf>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(path) => vec![path],
+        OneOrMany::Many(paths) => paths,
+    })
+}
+
+/// Директория, относительно которой разрешаются пути из конфига.
+pub fn base_dir(config_path: &Path) -> PathBuf {
+    match config_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,4 +514,129 @@ mod tests {
         assert_eq!(config.top.unwrap().text, "Top text");
         assert_eq!(config.down.unwrap().text, "Down text");
     }
+
+    #[test]
+    fn test_extends_merge() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let shared = temp_dir.path().join("shared");
+        let project = temp_dir.path().join("project");
+        std::fs::create_dir_all(&shared).unwrap();
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            shared.join("base.toml"),
+            r#"
+            [top]
+            text = "Base"
+            file = "preamble.md"
+            [files]
+            include = ["*.rs", "Cargo.toml"]
+            "#,
+        )
+        .unwrap();
+        std::fs::write(
+            project.join("ginpee.toml"),
+            r#"
+            extends = ["../shared/base.toml"]
+            [top]
+            text = "Child"
+            [files]
+            include = ["*.md"]
+            "#,
+        )
+        .unwrap();
+
+        let config = load(&project.join("ginpee.toml")).unwrap();
+        let top = config.top.unwrap();
+        assert_eq!(top.text, "Child");
+        assert_eq!(top.file, vec![PathBuf::from("../shared/preamble.md")]);
+        assert_eq!(config.files.unwrap().include, vec!["*.rs", "Cargo.toml", "*.md"]);
+
+        std::fs::write(
+            project.join("ginpee.toml"),
+            "extends = \"../shared/base.toml\"\n[files]\ninclude = [\"!override\", \"*.md\"]\n",
+        )
+        .unwrap();
+        let config = load(&project.join("ginpee.toml")).unwrap();
+        assert_eq!(config.files.unwrap().include, vec!["*.md"]);
+    }
+
+    #[test]
+    fn test_extends_cycle() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.toml"), "extends = [\"b.toml\"]").unwrap();
+        std::fs::write(temp_dir.path().join("b.toml"), "extends = [\"a.toml\"]").unwrap();
+
+        let error = load(&temp_dir.path().join("a.toml")).unwrap_err();
+        assert!(error.to_string().contains("config inheritance cycle"));
+    }
+
+    #[test]
+    fn test_discover_fallback_order() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("ginpee.toml");
+        std::fs::write(
+            temp_dir.path().join("package.json"),
+            r#"{ "name": "app", "ginpee": { "files": { "include": ["*.ts"] } } }"#,
+        )
+        .unwrap();
+
+        let (config, source) = discover(&config_path).unwrap();
+        assert_eq!(source, Source::PackageJson(temp_dir.path().join("package.json")));
+        assert_eq!(config.files.unwrap().include, vec!["*.ts"]);
+
+        std::fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\n\n[package.metadata.ginpee.files]\ninclude = [\"*.rs\"]\n",
+        )
+        .unwrap();
+        let (config, source) = discover(&config_path).unwrap();
+        assert_eq!(source, Source::CargoToml(temp_dir.path().join("Cargo.toml")));
+        assert_eq!(config.files.unwrap().include, vec!["*.rs"]);
+
+        std::fs::write(&config_path, "[files]\ninclude = [\"*.md\"]\n").unwrap();
+        let (_, source) = discover(&config_path).unwrap();
+        assert_eq!(source, Source::File(config_path));
+    }
+
+    #[test]
+    fn test_explicit_missing_config_is_an_error() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\n\n[package.metadata.ginpee.files]\ninclude = [\"*.rs\"]\n",
+        )
+        .unwrap();
+        let config_path = temp_dir.path().join("custom.toml");
+
+        let error = resolve(Some(&config_path)).unwrap_err().to_string();
+        assert!(error.contains("custom.toml not found"), "{}", error);
+
+        std::fs::write(&config_path, "[files]\ninclude = [\"*.md\"]\n").unwrap();
+        let (_, source) = resolve(Some(&config_path)).unwrap();
+        assert_eq!(source, Source::File(config_path));
+    }
+
+    #[test]
+    fn test_content_from_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.md"), "Preamble A\n").unwrap();
+        std::fs::write(temp_dir.path().join("b.md"), "Preamble B").unwrap();
+        let config_path = temp_dir.path().join("ginpee.toml");
+        std::fs::write(
+            &config_path,
+            r#"
+            [top]
+            text = "Intro"
+            file = ["a.md", "b.md"]
+            [down]
+            file = "a.md"
+            "#,
+        )
+        .unwrap();
+
+        let config = load(&config_path).unwrap();
+        let base = base_dir(&config_path);
+        assert_eq!(config.top.unwrap().resolve(&base).unwrap(), "Intro\nPreamble A\nPreamble B");
+        assert_eq!(config.down.unwrap().resolve(&base).unwrap(), "Preamble A");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,290 @@
+//! Модуль правки списков `include` / `exclude` в `ginpee.toml` с сохранением форматирования.
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, DocumentMut, Item, Table};
+
+use crate::collector::{self, CollectOptions, PatternSet};
+use crate::config;
+
+/// Какой список правим и где он лежит.
+#[derive(Debug, Clone, Copy)]
+pub struct Target<'a> {
+    /// `exclude` вместо `include`
+    pub exclude: bool,
+    /// `[profiles.NAME]` вместо `[files]`
+    pub profile: Option<&'a str>,
+    /// Путь к таблице ginpee в файле: пустой для `ginpee.toml`, `["tool", "ginpee"]` для `pyproject.toml`
+    pub table: &'a [&'a str],
+}
+
+impl Target<'_> {
+    fn list(&self) -> &'static str {
+        if self.exclude { "exclude" } else { "include" }
+    }
+
+    fn describe(&self) -> String {
+        let prefix: String = self.table.iter().map(|key| format!("{}.", key)).collect();
+        match self.profile {
+            Some(name) => format!("[{}profiles.{}] {}", prefix, name, self.list()),
+            None => format!("[{}files] {}", prefix, self.list()),
+        }
+    }
+}
+
+/// Добавляет паттерны, которых ещё нет в списке; возвращает новое содержимое и добавленные паттерны.
+pub fn add_patterns<'p>(
+    content: &str,
+    target: Target,
+    patterns: &'p [String],
+) -> Result<(String, Vec<&'p str>), Box<dyn std::error::Error>> {
+    let mut doc: DocumentMut = content.parse()?;
+    let array = list_mut(&mut doc, target)?;
+
+    let mut added = Vec::new();
+    for pattern in patterns {
+        if !array.iter().any(|v| v.as_str() == Some(pattern.as_str())) {
+            push_formatted(array, pattern);
+            added.push(pattern.as_str());
+        }
+    }
+    Ok((doc.to_string(), added))
+}
+
+/// Удаляет паттерны из списка; возвращает новое содержимое и реально удалённые паттерны.
+pub fn remove_patterns<'p>(
+    content: &str,
+    target: Target,
+    patterns: &'p [String],
+) -> Result<(String, Vec<&'p str>), Box<dyn std::error::Error>> {
+    let mut doc: DocumentMut = content.parse()?;
+    let array = list_mut(&mut doc, target)?;
+
+    let mut removed = Vec::new();
+    for pattern in patterns {
+        let before = array.len();
+        array.retain(|v| v.as_str() != Some(pattern.as_str()));
+        if array.len() != before {
+            removed.push(pattern.as_str());
+        }
+    }
+    // В однострочном массиве у первого элемента не должно остаться пробела после `[`
+    if let Some(first) = array.get_mut(0) {
+        let inline = first.decor().prefix().and_then(|p| p.as_str()).is_some_and(|p| !p.contains('\n'));
+        if inline {
+            first.decor_mut().set_prefix("");
+        }
+    }
+    Ok((doc.to_string(), removed))
+}
+
+/// Заменяет список целиком (используется `ginpee pick`); каждый паттерн — на своей строке.
+pub fn set_patterns(
+    content: &str,
+    target: Target,
+    patterns: &[String],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut doc: DocumentMut = content.parse()?;
+    let array = list_mut(&mut doc, target)?;
+
+    array.clear();
+    for pattern in patterns {
+        array.push_formatted(toml_edit::Value::from(pattern.as_str()).decorated("\n    ", ""));
+    }
+    array.set_trailing("\n");
+    array.set_trailing_comma(!patterns.is_empty());
+    Ok(doc.to_string())
+}
+
+/// `ginpee add`: проверяет паттерны, дописывает их в конфиг и печатает число совпадений.
+pub fn run_add(
+    explicit: Option<&Path>,
+    target: Target,
+    patterns: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Явно указанный конфиг, которого ещё нет, `add` создаёт сам
+    let (config, source) = match explicit {
+        Some(path) if !path.exists() => (config::Config::default(), config::Source::Default),
+        _ => config::resolve(explicit)?,
+    };
+    let (config_path, table) = config::edit_location(&source, explicit)?;
+    let target = Target { table, ..target };
+    // Профиль, который создаст эта же команда, наследует `[files]`
+    let profile = target.profile.filter(|name| config.profiles.contains_key(*name));
+    let selection = config.selection(profile)?;
+    PatternSet::with_options(patterns, selection.match_options())?;
+    let options = count_options(&config, &selection, target);
+
+    let content = fs::read_to_string(&config_path).unwrap_or_default();
+    let (updated, added) = add_patterns(&content, target, patterns)?;
+
+    for pattern in patterns {
+        let matched = count_matches(pattern, &options)?;
+        if added.contains(&pattern.as_str()) {
+            println!("Added `{}` to {} ({} files match)", pattern, target.describe(), matched);
+        } else {
+            println!("`{}` is already in {} ({} files match)", pattern, target.describe(), matched);
+        }
+    }
+    if !added.is_empty() {
+        fs::write(&config_path, updated)?;
+    }
+    Ok(())
+}
+
+/// `ginpee remove`: убирает паттерны из конфига.
+pub fn run_remove(
+    explicit: Option<&Path>,
+    target: Target,
+    patterns: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (_, source) = config::resolve(explicit)?;
+    let (config_path, table) = config::edit_location(&source, explicit)?;
+    let target = Target { table, ..target };
+    let content = fs::read_to_string(&config_path)
+        .map_err(|e| format!("failed to read {}: {}", config_path.display(), e))?;
+    let (updated, removed) = remove_patterns(&content, target, patterns)?;
+
+    for pattern in patterns {
+        if removed.contains(&pattern.as_str()) {
+            println!("Removed `{}` from {}", pattern, target.describe());
+        } else {
+            println!("`{}` is not in {}", pattern, target.describe());
+        }
+    }
+    if !removed.is_empty() {
+        fs::write(&config_path, updated)?;
+    }
+    Ok(())
+}
+
+/// Обход для подсчёта совпадений: как в `ginpee run` (скрытые файлы, ссылки, `max_depth`, сам результат),
+/// но без лимитов на число и размер файлов — подсчёт не должен падать там, где упадёт `run`.
+fn count_options(config: &config::Config, selection: &config::Files, target: Target) -> CollectOptions {
+    let output = config
+        .output
+        .as_ref()
+        .and_then(|o| o.path.clone())
+        .unwrap_or_else(|| PathBuf::from("project.md"));
+    let backups = config.output.as_ref().and_then(|o| o.backups).unwrap_or(0);
+    let mut options = crate::collect_options(config, selection, &output, backups);
+    options.limits.max_files = None;
+    options.limits.max_total_bytes = None;
+    // Исключающий паттерн считаем сам по себе, включающий — с учётом текущих `exclude`
+    if target.exclude {
+        options.exclude.clear();
+    }
+    options
+}
+
+/// Сколько файлов проекта сейчас подходит под паттерн (с учётом `.gpskip` и `[walk]`).
+fn count_matches(pattern: &str, options: &CollectOptions) -> Result<usize, Box<dyn std::error::Error>> {
+    let base_path = std::env::current_dir()?;
+    let collection = collector::collect(&base_path, &[pattern.to_string()], options)?;
+    Ok(collection.files.len())
+}
+
+/// Добавляет элемент с тем же отступом, что у последнего (важно для многострочных массивов).
+pub(crate) fn push_formatted(array: &mut Array, pattern: &str) {
+    let prefix = array
+        .iter()
+        .last()
+        .and_then(|last| last.decor().prefix())
+        .and_then(|p| p.as_str())
+        .map(str::to_string);
+    array.push(pattern);
+    if let (Some(prefix), Some(new)) = (prefix, array.iter_mut().last()) {
+        let prefix = if prefix.is_empty() { " ".to_string() } else { prefix };
+        new.decor_mut().set_prefix(prefix);
+    }
+}
+
+/// Находит (или создаёт) нужный массив в документе.
+fn list_mut<'d>(doc: &'d mut DocumentMut, target: Target) -> Result<&'d mut Array, Box<dyn std::error::Error>> {
+    let mut root = doc.as_table_mut();
+    for key in target.table {
+        root = table_mut(root, key)?;
+    }
+    let table = match target.profile {
+        Some(name) => {
+            let profiles = table_mut(root, "profiles")?;
+            profiles.set_implicit(true);
+            table_mut(profiles, name)?
+        }
+        None => table_mut(root, "files")?,
+    };
+
+    table
+        .entry(target.list())
+        .or_insert_with(|| Item::Value(Array::new().into()))
+        .as_array_mut()
+        .ok_or_else(|| format!("{} must be an array", target.describe()).into())
+}
+
+fn table_mut<'d>(parent: &'d mut Table, key: &str) -> Result<&'d mut Table, Box<dyn std::error::Error>> {
+    parent
+        .entry(key)
+        .or_insert(Item::Table(Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| format!("`{}` must be a table", key).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_add_and_remove_preserve_formatting() {
+        let content = "# project config\n[files]\ninclude = [\"*.rs\"] # sources\n";
+        let target = Target { exclude: false, profile: None, table: &[] };
+
+        let to_add = patterns(&["*.rs", "Cargo.toml"]);
+        let (updated, added) = add_patterns(content, target, &to_add).unwrap();
+        assert_eq!(added, vec!["Cargo.toml"]);
+        assert_eq!(updated, "# project config\n[files]\ninclude = [\"*.rs\", \"Cargo.toml\"] # sources\n");
+
+        let to_remove = patterns(&["*.rs", "*.md"]);
+        let (updated, removed) = remove_patterns(&updated, target, &to_remove).unwrap();
+        assert_eq!(removed, vec!["*.rs"]);
+        assert_eq!(updated, "# project config\n[files]\ninclude = [\"Cargo.toml\"] # sources\n");
+    }
+
+    #[test]
+    fn test_add_to_multiline_array() {
+        let content = "[files]\ninclude = [\n    \"*.rs\",\n]\n";
+        let target = Target { exclude: false, profile: None, table: &[] };
+
+        let (updated, _) = add_patterns(content, target, &patterns(&["Cargo.toml"])).unwrap();
+        assert_eq!(updated, "[files]\ninclude = [\n    \"*.rs\",\n    \"Cargo.toml\",\n]\n");
+    }
+
+    #[test]
+    fn test_set_patterns() {
+        let target = Target { exclude: false, profile: None, table: &[] };
+
+        let updated = set_patterns("[files] # picked\ninclude = [\"*.rs\"]\n", target, &patterns(&["a.rs", "b.rs"])).unwrap();
+        assert_eq!(updated, "[files] # picked\ninclude = [\n    \"a.rs\",\n    \"b.rs\",\n]\n");
+    }
+
+    #[test]
+    fn test_add_to_manifest_table() {
+        let content = "[package]\nname = \"app\"\n\n[package.metadata.ginpee]\ntop = { text = \"Intro\" }\n";
+        let target = Target { exclude: false, profile: None, table: &["package", "metadata", "ginpee"] };
+
+        let (updated, _) = add_patterns(content, target, &patterns(&["*.rs"])).unwrap();
+        assert_eq!(updated, format!("{}\n[package.metadata.ginpee.files]\ninclude = [\"*.rs\"]\n", content));
+        assert_eq!(target.describe(), "[package.metadata.ginpee.files] include");
+    }
+
+    #[test]
+    fn test_add_to_profile_exclude() {
+        let target = Target { exclude: true, profile: Some("docs"), table: &[] };
+
+        let (updated, _) = add_patterns("[files]\ninclude = []\n", target, &patterns(&["*.rs"])).unwrap();
+        let config: crate::config::Config = toml::from_str(&updated).unwrap();
+        assert_eq!(config.profiles["docs"].exclude, vec!["*.rs"]);
+    }
+}
@@ -0,0 +1,113 @@
+//! Модуль эвристик для режима «все текстовые файлы»: бинарные, сгенерированные и lock-файлы.
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Сколько байт из начала файла смотрим при определении типа.
+const SNIFF_LEN: usize = 8192;
+
+const LOCKFILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+    "flake.lock",
+    "mix.lock",
+    "pubspec.lock",
+    "Podfile.lock",
+    "packages.lock.json",
+];
+
+const GENERATED_SUFFIXES: &[&str] = &[".min.js", ".min.css", ".map", ".pb.go", "_pb2.py", ".g.dart"];
+
+/// Маркеры в первых строках, которыми генераторы помечают свои файлы.
+const GENERATED_MARKERS: &[&str] = &["@generated", "DO NOT EDIT", "Code generated by", "auto-generated"];
+
+/// Почему файл не подходит для режима «все текстовые файлы».
+#[derive(Debug, PartialEq, Eq)]
+pub enum Skip {
+    Lockfile,
+    Generated,
+    Binary,
+}
+
+/// Классифицирует файл; `None` — обычный текстовый файл.
+pub fn classify(path: &Path) -> Option<Skip> {
+    let name = path.file_name()?.to_string_lossy();
+    if LOCKFILES.contains(&name.as_ref()) || name.ends_with(".lock") {
+        return Some(Skip::Lockfile);
+    }
+    if GENERATED_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)) {
+        return Some(Skip::Generated);
+    }
+
+    let head = read_head(path)?;
+    if is_binary(&head) {
+        return Some(Skip::Binary);
+    }
+    let first_lines: String = String::from_utf8_lossy(&head).lines().take(5).collect();
+    if GENERATED_MARKERS.iter().any(|marker| first_lines.contains(marker)) {
+        return Some(Skip::Generated);
+    }
+    None
+}
+
+/// Как у git: NUL-байт в начале файла означает бинарные данные. Не-UTF-8 текст (например, Latin-1)
+/// тоже пропускаем — встроить его всё равно не получится. Последовательность, обрезанная
+/// на границе `SNIFF_LEN`, ошибкой не считается.
+pub fn is_binary(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => false,
+        Err(e) => e.error_len().is_some() || head.len() < SNIFF_LEN,
+    }
+}
+
+fn read_head(path: &Path) -> Option<Vec<u8>> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)
+        .ok()?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .ok()?;
+    Some(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_classify() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = |name: &str| temp_dir.path().join(name);
+        fs::write(path("main.rs"), "fn main() {}").unwrap();
+        fs::write(path("Cargo.lock"), "# This file is automatically @generated by Cargo.").unwrap();
+        fs::write(path("logo.png"), [0x89, b'P', b'N', b'G', 0, 0, 0, 13]).unwrap();
+        fs::write(path("schema.rs"), "// @generated by diesel\npub struct X;").unwrap();
+        fs::write(path("app.min.js"), "var a=1").unwrap();
+        fs::write(path("notes.txt"), b"caf\xe9 cr\xe8me").unwrap();
+        // Двухбайтовый символ начинается на последнем прочитанном байте
+        fs::write(path("long.md"), format!("a{}", "ж".repeat(SNIFF_LEN))).unwrap();
+
+        assert_eq!(classify(&path("main.rs")), None);
+        assert_eq!(classify(&path("Cargo.lock")), Some(Skip::Lockfile));
+        assert_eq!(classify(&path("logo.png")), Some(Skip::Binary));
+        assert_eq!(classify(&path("schema.rs")), Some(Skip::Generated));
+        assert_eq!(classify(&path("app.min.js")), Some(Skip::Generated));
+        assert_eq!(classify(&path("notes.txt")), Some(Skip::Binary));
+        assert_eq!(classify(&path("long.md")), None);
+    }
+}
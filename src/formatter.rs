@@ -1,32 +1,250 @@
 //! Модуль формирования дерева и содержимого файлов для `project.md`.
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::cache::Cache;
+use crate::collector::Link;
+use crate::mode::{Mode, Modes};
+use crate::outline;
+use crate::tree::{self, Leaf, Tree};
 
 pub struct FileContent {
     pub path: String,
+    /// Как встроено содержимое (`content` уже обработано)
+    pub mode: Mode,
     pub content: String,
 }
 
 pub fn build_tree_and_content(
-    files: &[std::path::PathBuf],
+    files: &[PathBuf],
+    base_path: &Path,
+) -> Result<(String, Vec<FileContent>), Box<dyn std::error::Error>> {
+    build_with_links(files, &BTreeMap::new(), base_path)
+}
+
+/// То же, но файлы-ссылки показываются в дереве как `путь -> цель`,
+/// а их содержимое встраивается, только если `Link::embed`.
+pub fn build_with_links(
+    files: &[PathBuf],
+    links: &BTreeMap<PathBuf, Link>,
+    base_path: &Path,
+) -> Result<(String, Vec<FileContent>), Box<dyn std::error::Error>> {
+    build_with(files, links, base_path, &LayoutOptions::default())
+}
+
+/// То же с настройками [`layout_with`]: содержимое проходит через режимы встраивания.
+pub fn build_with(
+    files: &[PathBuf],
+    links: &BTreeMap<PathBuf, Link>,
     base_path: &Path,
+    options: &LayoutOptions,
 ) -> Result<(String, Vec<FileContent>), Box<dyn std::error::Error>> {
-    let mut tree = String::new();
-    let mut contents = Vec::new();
+    let layout = layout_with(files, links, base_path, options)?;
+    let contents = layout
+        .entries
+        .iter()
+        .map(|entry| {
+            Ok(FileContent {
+                path: entry.path.clone(),
+                mode: entry.mode,
+                content: entry.read()?,
+            })
+        })
+        .collect::<Result<_, Box<dyn std::error::Error>>>()?;
+    Ok((layout.tree, contents))
+}
+
+/// Что попадёт в результат, без содержимого файлов: дерево и список встраиваемых файлов.
+///
+/// Строится только по метаданным, тела файлов читаются по одному при записи.
+pub struct Layout {
+    pub tree: String,
+    pub entries: Vec<Entry>,
+}
 
-    for file_path in files {
+/// Встраиваемый файл: путь для заголовка и где взять содержимое.
+#[derive(Clone)]
+pub struct Entry {
+    /// Путь относительно корня проекта, через `/`
+    pub path: String,
+    pub source: PathBuf,
+    pub size: u64,
+    /// Как встраивать содержимое
+    pub mode: Mode,
+    /// Кэш обработанного содержимого; без него файл читается и обрабатывается каждый раз
+    pub cache: Option<Arc<Cache>>,
+}
+
+impl Entry {
+    /// Содержимое файла в том виде, в котором оно попадёт в результат.
+    pub fn read(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let mode = self.mode;
+        let result = match &self.cache {
+            // Полный текст — это сам файл: кэш только удвоил бы чтение и место на диске
+            Some(cache) if mode != Mode::Full => cache.load(&self.source, &mode.to_string(), |content| Ok(mode.apply(&self.path, content))),
+            _ => fs::read_to_string(&self.source)
+                .map(|content| mode.apply(&self.path, content))
+                .map_err(Into::into),
+        };
+        result.map_err(|e| format!("failed to read {}: {}", self.source.display(), e).into())
+    }
+}
+
+impl Layout {
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    /// Читать содержимое через кэш.
+    pub fn with_cache(mut self, cache: Arc<Cache>) -> Self {
+        for entry in &mut self.entries {
+            entry.cache = Some(Arc::clone(&cache));
+        }
+        self
+    }
+}
+
+/// Настройки [`layout_with`].
+#[derive(Default)]
+pub struct LayoutOptions<'a> {
+    /// Файлы, которые попадают только в дерево (по имени); отобранные тогда помечаются `*`
+    pub context: &'a [PathBuf],
+    pub tree: tree::Options,
+    /// Режимы встраивания из `[files] modes`; без них все файлы встраиваются целиком
+    pub modes: Option<&'a Modes>,
+}
+
+pub fn layout(
+    files: &[PathBuf],
+    links: &BTreeMap<PathBuf, Link>,
+    base_path: &Path,
+) -> Result<Layout, Box<dyn std::error::Error>> {
+    layout_with(files, links, base_path, &LayoutOptions::default())
+}
+
+/// То же с настройками; при `tree.annotate` файлы читаются, чтобы посчитать строки.
+pub fn layout_with(
+    files: &[PathBuf],
+    links: &BTreeMap<PathBuf, Link>,
+    base_path: &Path,
+    options: &LayoutOptions,
+) -> Result<Layout, Box<dyn std::error::Error>> {
+    let context = options.context;
+    let tree_options = &options.tree;
+    let mut tree = Tree::new();
+    let mut entries = Vec::new();
+
+    let selected: BTreeSet<&PathBuf> = files.iter().collect();
+    let mut all: Vec<&PathBuf> = files.iter().chain(context.iter().filter(|p| !selected.contains(p))).collect();
+    if !context.is_empty() {
+        all.sort();
+    }
+
+    for file_path in all {
         let relative_path = file_path.strip_prefix(base_path)?;
         let path_str = relative_path.to_string_lossy().replace('\\', "/"); // для Windows
-        tree.push_str(&format!("{}\n", path_str));
+        let link = links.get(file_path);
+        let mut leaf = if tree_options.annotate {
+            Leaf::from_disk(file_path)
+        } else {
+            Leaf { size: fs::metadata(file_path)?.len(), ..Leaf::default() }
+        };
+        leaf.link = link.map(|link| link.target.to_string_lossy().replace('\\', "/"));
+        let size = leaf.size;
+        let mode = match options.modes.map_or(Mode::Full, |modes| modes.for_path(relative_path)) {
+            // Без разбора языка `outline` — это тот же полный файл, так и показываем в заголовке
+            Mode::Outline if !outline::supported(language(&path_str)) => Mode::Full,
+            mode => mode,
+        };
+        let included = selected.contains(file_path) && mode != Mode::NameOnly;
+        leaf.marked = included && !context.is_empty();
+        tree.insert(&path_str, leaf);
+        if !included || link.is_some_and(|link| !link.embed) {
+            continue;
+        }
 
-        let content = fs::read_to_string(file_path)?;
-        contents.push(FileContent {
-            path: path_str.to_string(),
-            content,
+        entries.push(Entry {
+            path: path_str,
+            source: file_path.clone(),
+            size,
+            mode,
+            cache: None,
         });
     }
 
-    Ok((tree, contents))
+    let mut tree = tree.render(tree_options);
+    if !context.is_empty() {
+        tree.push_str("\n* contents included below\n");
+    }
+    Ok(Layout { tree, entries })
+}
+
+/// Грубая оценка числа токенов: ~4 байта на токен.
+pub fn estimate_tokens(bytes: u64) -> u64 {
+    bytes.div_ceil(4)
+}
+
+/// Размер в человекочитаемом виде: `512 B`, `4.1 KiB`, `12.0 MiB`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Язык файла по расширению или имени (для шаблонов и подсветки).
+pub fn language(path: &str) -> &'static str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name {
+        "Makefile" | "makefile" | "GNUmakefile" => return "make",
+        "Dockerfile" => return "dockerfile",
+        "CMakeLists.txt" => return "cmake",
+        _ => {}
+    }
+    let ext = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => ext,
+        _ => return "",
+    };
+    match ext {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" | "mts" | "cts" => "typescript",
+        "jsx" => "jsx",
+        "tsx" => "tsx",
+        "go" => "go",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "rb" => "ruby",
+        "php" => "php",
+        "cs" => "csharp",
+        "swift" => "swift",
+        "nix" => "nix",
+        "sh" | "bash" | "zsh" => "bash",
+        "fish" => "fish",
+        "lua" => "lua",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "json" => "json",
+        "xml" => "xml",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "sql" => "sql",
+        "md" | "markdown" => "markdown",
+        _ => "",
+    }
 }
 
 #[cfg(test)]
@@ -47,4 +265,49 @@ mod tests {
         assert!(tree.contains("main.rs"));
         assert_eq!(contents[0].content, "// test");
     }
+
+    #[test]
+    fn test_build_with_links() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("lib.rs");
+        let link = temp_dir.path().join("alias.rs");
+        fs::write(&file, "// lib").unwrap();
+        fs::write(&link, "// lib").unwrap();
+
+        let links = BTreeMap::from([(link.clone(), Link { target: PathBuf::from("lib.rs"), embed: false })]);
+        let (tree, contents) = build_with_links(&[file, link], &links, temp_dir.path()).unwrap();
+
+        assert_eq!(tree, "├── lib.rs\n└── alias.rs -> lib.rs\n");
+        assert_eq!(contents.len(), 1);
+    }
+
+    #[test]
+    fn test_build_with_modes() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("main.rs");
+        fs::write(&file, "fn main() {\n    run();\n}\n").unwrap();
+        let rules = vec![crate::mode::Rule { glob: "*.rs".to_string(), mode: Mode::Outline }];
+        let modes = Modes::new(&rules, Default::default()).unwrap();
+        let options = LayoutOptions { modes: Some(&modes), ..LayoutOptions::default() };
+
+        let (_, contents) = build_with(&[file], &BTreeMap::new(), temp_dir.path(), &options).unwrap();
+
+        assert_eq!(contents[0].mode, Mode::Outline);
+        assert_eq!(contents[0].content, "1  fn main() { … }");
+    }
+
+    #[test]
+    fn test_human_size() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(4300), "4.2 KiB");
+        assert_eq!(human_size(3 * 1024 * 1024), "3.0 MiB");
+    }
+
+    #[test]
+    fn test_language() {
+        assert_eq!(language("src/main.rs"), "rust");
+        assert_eq!(language("Makefile"), "make");
+        assert_eq!(language("dotfiles/mako/config"), "");
+        assert_eq!(language(".bashrc"), "");
+    }
 }
\ No newline at end of file
@@ -2,6 +2,9 @@
 use std::fs;
 use std::path::Path;
 
+use crate::edit;
+use crate::presets::{self, Preset};
+
 const DEFAULT_GPSKIP_CONTENT: &str = r#"
 .git/
 .vscode/
@@ -26,47 +29,261 @@ text = ""
 text = ""
 
 [files]
+matching = "gitignore"
 include = []
 "#;
 
+/// Ключи, которые `--update` не добавляет: в старом конфиге они изменили бы поведение.
+const UPDATE_SKIP_KEYS: &[&str] = &["matching"];
+
+/// Границы секции `.gpskip`, которой управляет ginpee; всё вне её принадлежит пользователю.
+const MANAGED_BEGIN: &str = "# >>> ginpee managed >>>";
+const MANAGED_END: &str = "# <<< ginpee managed <<<";
+
+#[derive(Debug, Default, Clone)]
+pub struct InitOptions {
+    /// Перезаписать существующие файлы
+    pub force: bool,
+    /// Дополнить существующие файлы новыми значениями по умолчанию, не перезаписывая их
+    pub update: bool,
+    /// Пресеты, выбранные явно; пустой список — определить автоматически
+    pub presets: Vec<String>,
+}
+
 pub fn run(force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    run_with(&InitOptions {
+        force,
+        ..InitOptions::default()
+    })
+}
+
+/// Печатает доступные пресеты и маркеры, по которым они определяются.
+pub fn list_presets() {
+    for preset in presets::PRESETS {
+        println!(
+            "{:<8} {} (detected by: {})",
+            preset.name,
+            preset.description,
+            preset.markers.join(", ")
+        );
+    }
+}
+
+pub fn run_with(options: &InitOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let selected = select_presets(&options.presets, Path::new("."))?;
+    if selected.is_empty() {
+        println!("No project type detected: include will match all text files.");
+    } else {
+        let names: Vec<_> = selected.iter().map(|p| p.name).collect();
+        println!("Using presets: {}", names.join(", "));
+    }
+
+    let mut gpskip_entries: Vec<String> = DEFAULT_GPSKIP_CONTENT
+        .lines()
+        .chain(presets::merged(&selected, |p| p.skip))
+        .map(str::to_string)
+        .collect();
+    if Path::new(".gitignore").exists() {
+        gpskip_entries.extend(fs::read_to_string(".gitignore")?.lines().map(str::to_string));
+    }
+
+    if options.update {
+        let toml_path = Path::new("ginpee.toml");
+        let old = fs::read_to_string(toml_path).unwrap_or_default();
+        let new = if old.is_empty() {
+            ginpee_toml(&selected)
+        } else {
+            merge_ginpee_toml(&old, &selected)?
+        };
+        write_with_diff(toml_path, &old, &new)?;
+
+        let gpskip_path = Path::new(".gpskip");
+        let old = fs::read_to_string(gpskip_path).unwrap_or_default();
+        write_with_diff(gpskip_path, &old, &merge_gpskip(&old, &gpskip_entries))?;
+        return Ok(());
+    }
+
     let mut created = 0;
 
     // ginpee.toml
     let toml_path = Path::new("ginpee.toml");
-    if toml_path.exists() && !force {
-        println!("ginpee.toml already exists. Use --force to overwrite.");
+    if toml_path.exists() && !options.force {
+        println!("ginpee.toml already exists. Use --force to overwrite or --update to merge.");
     } else {
-        fs::write(toml_path, DEFAULT_GINPEE_TOML_CONTENT)?;
+        fs::write(toml_path, ginpee_toml(&selected))?;
         println!("Created ginpee.toml");
         created += 1;
     }
 
     // .gpskip
     let gpskip_path = Path::new(".gpskip");
-    let mut gpskip_content = DEFAULT_GPSKIP_CONTENT.to_string();
-
-    if Path::new(".gitignore").exists() {
-        let gitignore_content = fs::read_to_string(".gitignore")?;
-        gpskip_content.push_str(&gitignore_content);
-    }
-
-    if gpskip_path.exists() && !force {
-        println!(".gpskip already exists. Use --force to overwrite.");
+    if gpskip_path.exists() && !options.force {
+        println!(".gpskip already exists. Use --force to overwrite or --update to merge.");
     } else {
-        fs::write(gpskip_path, gpskip_content)?;
+        fs::write(gpskip_path, merge_gpskip("", &gpskip_entries))?;
         println!("Created .gpskip");
         created += 1;
     }
 
     println!("Total files created: {}", created);
     if created == 0 {
-        println!("Nothing to create. Use --force to overwrite existing files.");
+        println!("Nothing to create. Use --force to overwrite or --update to merge existing files.");
     }
 
     Ok(())
 }
 
+/// Явно указанные пресеты или, если их нет, найденные по маркерным файлам.
+fn select_presets(
+    names: &[String],
+    dir: &Path,
+) -> Result<Vec<&'static Preset>, Box<dyn std::error::Error>> {
+    if names.is_empty() {
+        return Ok(presets::detect(dir));
+    }
+    names
+        .iter()
+        .map(|name| {
+            presets::find(name).ok_or_else(|| {
+                format!("unknown preset `{}` (see `ginpee init --list-presets`)", name).into()
+            })
+        })
+        .collect()
+}
+
+fn ginpee_toml(selected: &[&Preset]) -> String {
+    if selected.is_empty() {
+        return DEFAULT_GINPEE_TOML_CONTENT.to_string();
+    }
+
+    let names: Vec<_> = selected.iter().map(|p| p.name).collect();
+    let mut content = DEFAULT_GINPEE_TOML_CONTENT.replace(
+        "include = []\n",
+        &format!("# presets: {}\ninclude = [\n", names.join(", ")),
+    );
+    for pattern in presets::merged(selected, |p| p.include) {
+        content.push_str(&format!("    \"{}\",\n", pattern));
+    }
+    content.push_str("]\n");
+    content
+}
+
+/// Дописывает в существующий `ginpee.toml` недостающие ключи по умолчанию и паттерны
+/// пресетов, сохраняя комментарии и форматирование.
+///
+/// Пустой `include` («все текстовые файлы») не трогаем: паттерны пресетов его бы сузили.
+fn merge_ginpee_toml(existing: &str, selected: &[&Preset]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut doc: toml_edit::DocumentMut = existing.parse()?;
+    let defaults: toml_edit::DocumentMut = ginpee_toml(selected).parse()?;
+
+    for (section, defaults_item) in defaults.iter() {
+        let Some(defaults_table) = defaults_item.as_table() else {
+            continue;
+        };
+        let table = doc
+            .entry(section)
+            .or_insert_with(|| toml_edit::Item::Table(toml_edit::Table::new()))
+            .as_table_like_mut()
+            .ok_or_else(|| format!("ginpee.toml: `{}` must be a table", section))?;
+
+        for (key, value) in defaults_table.iter() {
+            if UPDATE_SKIP_KEYS.contains(&key) {
+                continue;
+            }
+            match table.get_mut(key) {
+                None => {
+                    table.insert(key, value.clone());
+                }
+                Some(existing) => {
+                    if let (Some(array), Some(default_array)) =
+                        (existing.as_array_mut(), value.as_array())
+                        && !array.is_empty()
+                    {
+                        for item in default_array.iter() {
+                            if !array.iter().any(|i| i.as_str() == item.as_str()) {
+                                edit::push_formatted(array, item.as_str().unwrap_or_default());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Собирает `.gpskip`: строки пользователя остаются как есть, а управляемая секция
+/// содержит только те записи, которых ещё нет вне её, без повторов.
+///
+/// Файл без секции (от старого `init`, дописывавшего `.gitignore` к значениям по умолчанию)
+/// принимается под управление: повторы правил убираются, комментарии и пустые строки остаются.
+fn merge_gpskip(existing: &str, entries: &[String]) -> String {
+    let mut user_lines: Vec<&str> = Vec::new();
+    let mut in_managed = false;
+    for line in existing.lines() {
+        match line.trim() {
+            MANAGED_BEGIN => in_managed = true,
+            MANAGED_END => in_managed = false,
+            _ if !in_managed => user_lines.push(line),
+            _ => {}
+        }
+    }
+    if !existing.lines().any(|line| line.trim() == MANAGED_BEGIN) {
+        let mut seen = Vec::new();
+        user_lines.retain(|line| {
+            let rule = line.trim();
+            if rule.is_empty() || rule.starts_with('#') {
+                return true;
+            }
+            if seen.contains(&rule) {
+                return false;
+            }
+            seen.push(rule);
+            true
+        });
+    }
+
+    let mut managed: Vec<&str> = Vec::new();
+    for entry in entries.iter().map(|e| e.trim()) {
+        let is_rule = !entry.is_empty() && !entry.starts_with('#');
+        if is_rule && !managed.contains(&entry) && !user_lines.iter().any(|l| l.trim() == entry) {
+            managed.push(entry);
+        }
+    }
+
+    let mut content = String::new();
+    for line in &user_lines {
+        content.push_str(line);
+        content.push('\n');
+    }
+    if !content.is_empty() && !content.ends_with("\n\n") {
+        content.push('\n');
+    }
+    content.push_str(MANAGED_BEGIN);
+    content.push('\n');
+    for entry in managed {
+        content.push_str(entry);
+        content.push('\n');
+    }
+    content.push_str(MANAGED_END);
+    content.push('\n');
+    content
+}
+
+fn write_with_diff(path: &Path, old: &str, new: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if old == new {
+        println!("{} is up to date", path.display());
+        return Ok(());
+    }
+    let name = path.display().to_string();
+    let diff = similar::TextDiff::from_lines(old, new);
+    print!("{}", diff.unified_diff().header(&name, &name));
+    fs::write(path, new)?;
+    println!("Updated {}", name);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -102,4 +319,69 @@ mod tests {
 
         std::env::set_current_dir(original_dir).unwrap();
     }
+
+    #[test]
+    fn test_ginpee_toml_for_presets() {
+        let selected = [presets::find("rust").unwrap(), presets::find("nix").unwrap()];
+        let content = ginpee_toml(&selected);
+
+        let config: crate::config::Config = toml::from_str(&content).unwrap();
+        assert_eq!(
+            config.files.unwrap().include,
+            vec!["*.rs", "Cargo.toml", "build.rs", "rust-toolchain.toml", "*.nix"]
+        );
+        assert!(content.contains("# presets: rust, nix"));
+    }
+
+    #[test]
+    fn test_merge_ginpee_toml_preserves_comments() {
+        let existing = "# my config\n[files]\n# sources\ninclude = [\"*.rs\", \"docs/*.md\"]\n";
+        let merged = merge_ginpee_toml(existing, &[presets::find("rust").unwrap()]).unwrap();
+
+        assert!(merged.starts_with("# my config\n"));
+        assert!(merged.contains("# sources\n"));
+        let config: crate::config::Config = toml::from_str(&merged).unwrap();
+        assert_eq!(
+            config.files.unwrap().include,
+            vec!["*.rs", "docs/*.md", "Cargo.toml", "build.rs", "rust-toolchain.toml"]
+        );
+        assert_eq!(config.top.unwrap().text, "");
+    }
+
+    #[test]
+    fn test_merge_gpskip_deduplicates() {
+        let entries: Vec<String> = ["target/", "*.log", "target/", "# comment", "/secret"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let existing = "project.md\n*.log\n";
+
+        let merged = merge_gpskip(existing, &entries);
+        assert_eq!(
+            merged,
+            format!("project.md\n*.log\n\n{}\ntarget/\n/secret\n{}\n", MANAGED_BEGIN, MANAGED_END)
+        );
+        assert_eq!(merge_gpskip(&merged, &entries), merged);
+
+        // Старый `init` дописывал `.gitignore` к значениям по умолчанию
+        let old = "target/\n*.log\n\n# from .gitignore\ntarget/\n*.log\n.env\n";
+        assert_eq!(
+            merge_gpskip(old, &entries),
+            format!("target/\n*.log\n\n# from .gitignore\n.env\n\n{}\n/secret\n{}\n", MANAGED_BEGIN, MANAGED_END)
+        );
+    }
+
+    #[test]
+    fn test_merge_ginpee_toml_keeps_multiline_include() {
+        let existing = "[files]\ninclude = [\n    \"*.md\",\n]\n";
+        let merged = merge_ginpee_toml(existing, &[presets::find("go").unwrap()]).unwrap();
+
+        assert!(merged.contains("include = [\n    \"*.md\",\n    \"*.go\",\n    \"go.mod\",\n]\n"), "{}", merged);
+    }
+
+    #[test]
+    fn test_unknown_preset() {
+        let error = select_presets(&["cobol".to_string()], Path::new(".")).err().unwrap();
+        assert!(error.to_string().contains("unknown preset `cobol`"));
+    }
 }
@@ -0,0 +1,139 @@
+//! Модуль подстановки `${VAR}` и `${VAR:-default}` в строковые значения конфига.
+use std::collections::BTreeMap;
+use toml::{Table, Value};
+
+/// Подставляет переменные во все строки таблицы, кроме `[vars]`.
+///
+/// Значения из `[vars]` сами могут ссылаться только на переменные окружения;
+/// в остальных полях `[vars]` имеет приоритет над окружением.
+pub fn interpolate_table(table: &mut Table) -> Result<(), Box<dyn std::error::Error>> {
+    let no_vars = BTreeMap::new();
+    let mut vars = BTreeMap::new();
+
+    if let Some(value) = table.get_mut("vars") {
+        let Value::Table(user_vars) = value else {
+            return Err("`vars` must be a table of strings".into());
+        };
+        for (name, value) in user_vars.iter_mut() {
+            let Value::String(text) = value else {
+                return Err(format!("`vars.{}` must be a string", name).into());
+            };
+            *text = interpolate(text, &no_vars, &format!("vars.{}", name))?;
+            vars.insert(name.clone(), text.clone());
+        }
+    }
+
+    for (key, value) in table.iter_mut() {
+        if key != "vars" {
+            interpolate_value(value, &vars, key)?;
+        }
+    }
+    Ok(())
+}
+
+fn interpolate_value(
+    value: &mut Value,
+    vars: &BTreeMap<String, String>,
+    location: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match value {
+        Value::String(text) => *text = interpolate(text, vars, location)?,
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                interpolate_value(item, vars, &format!("{}[{}]", location, i))?;
+            }
+        }
+        Value::Table(table) => {
+            for (key, item) in table.iter_mut() {
+                interpolate_value(item, vars, &format!("{}.{}", location, key))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Подставляет переменные в одну строку; `$${` даёт буквальный `${`.
+pub fn interpolate(
+    text: &str,
+    vars: &BTreeMap<String, String>,
+    location: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start..];
+
+        if let Some(escaped) = after.strip_prefix("$${") {
+            result.push_str("${");
+            rest = escaped;
+            continue;
+        }
+        let Some(body) = after.strip_prefix("${") else {
+            result.push('$');
+            rest = &after[1..];
+            continue;
+        };
+        let end = body
+            .find('}')
+            .ok_or_else(|| format!("unterminated `${{` in `{}`", location))?;
+
+        let expr = &body[..end];
+        let (name, default) = match expr.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expr, None),
+        };
+        let value = vars
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok().filter(|v| default.is_none() || !v.is_empty()))
+            .or_else(|| default.map(str::to_string))
+            .ok_or_else(|| {
+                format!(
+                    "undefined variable `{}` in `{}` (define it in [vars] or the environment, or use ${{{}:-default}})",
+                    name, location, name
+                )
+            })?;
+
+        result.push_str(&value);
+        rest = &body[end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate() {
+        let mut vars = BTreeMap::new();
+        vars.insert("name".to_string(), "ginpee".to_string());
+
+        let text = "${name}-${GINPEE_SURELY_UNSET_VAR:-local}.md costs $5 and $${literal}";
+        assert_eq!(interpolate(text, &vars, "output.path").unwrap(), "ginpee-local.md costs $5 and ${literal}");
+
+        let error = interpolate("${GINPEE_SURELY_UNSET_VAR}", &vars, "files.include[0]").unwrap_err();
+        assert!(error.to_string().contains("`GINPEE_SURELY_UNSET_VAR` in `files.include[0]`"));
+    }
+
+    #[test]
+    fn test_interpolate_table() {
+        let mut table: Table = toml::from_str(
+            r#"
+            [vars]
+            dir = "${GINPEE_SURELY_UNSET_VAR:-src}"
+            [files]
+            include = ["${dir}/*.rs"]
+            "#,
+        )
+        .unwrap();
+
+        interpolate_table(&mut table).unwrap();
+        assert_eq!(table["files"]["include"][0].as_str(), Some("src/*.rs"));
+    }
+}
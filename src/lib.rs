@@ -1,65 +1,362 @@
+pub mod cache;
 pub mod config;
 pub mod collector;
+pub mod edit;
+pub mod filetype;
 pub mod formatter;
 pub mod init;
+pub mod interpolate;
+pub mod mode;
+pub mod outline;
+pub mod pick;
+pub mod presets;
+pub mod template;
+pub mod tree;
+pub mod vars;
 pub mod writer;
 
-pub use clap::{Subcommand, Parser};
+pub use clap::{Args, Subcommand, Parser};
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use cache::Cache;
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// Generate ginpee.toml and .gpskip
     Init {
         /// Force overwrite existing files
-        #[arg(long)]
+        #[arg(long, conflicts_with = "update")]
         force: bool,
-    },
-    /// Generate project.md
-    Run {
-        /// Output file (default: project.md)
-        #[arg(short, long, default_value = "project.md")]
-        output: std::path::PathBuf,
 
-        /// Top text to include in project.md
+        /// Merge new defaults into existing files instead of overwriting them
         #[arg(long)]
-        top: Option<String>,
+        update: bool,
 
-        /// Bottom text to include in project.md
-        #[arg(long)]
-        down: Option<String>,
+        /// Project preset to use instead of auto-detection (repeatable)
+        #[arg(long = "preset", value_name = "NAME")]
+        presets: Vec<String>,
 
-        /// Files to include (e.g., "*.rs" "README.md")
+        /// List available presets and exit
+        #[arg(long)]
+        list_presets: bool,
+    },
+    /// Generate project.md
+    Run(RunArgs),
+    /// Add include (or exclude) patterns to ginpee.toml
+    Add(EditArgs),
+    /// Remove include (or exclude) patterns from ginpee.toml
+    Remove(EditArgs),
+    /// Pick files interactively and save them to ginpee.toml or render project.md
+    Pick {
+        /// Path to config file (default: ginpee.toml)
         #[arg(long)]
-        files: Vec<String>,
+        config: Option<PathBuf>,
+    },
+    /// Inspect configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Manage the cache of processed file contents (.ginpee/cache)
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct EditArgs {
+    /// Patterns to add or remove (e.g., "src/**/*.rs")
+    #[arg(required = true)]
+    pub patterns: Vec<String>,
+
+    /// Edit the exclude list instead of include
+    #[arg(long)]
+    pub exclude: bool,
 
+    /// Edit [profiles.NAME] instead of [files]
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Path to config file (default: ginpee.toml)
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+}
+
+impl EditArgs {
+    pub fn target(&self) -> edit::Target<'_> {
+        edit::Target {
+            exclude: self.exclude,
+            profile: self.profile.as_deref(),
+            table: &[],
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print the fully resolved configuration (after `extends` and interpolation)
+    Show {
         /// Path to config file (default: ginpee.toml)
-        #[arg(long, default_value = "ginpee.toml")]
-        config: std::path::PathBuf,
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Print where the configuration was loaded from
+        #[arg(short, long)]
+        verbose: bool,
     },
 }
 
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Remove all cached file contents
+    Clear,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct RunArgs {
+    /// Output file (default: [output] path or project.md)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Top text to include in project.md (`@file` reads it from a file)
+    #[arg(long)]
+    pub top: Option<String>,
+
+    /// Bottom text to include in project.md (`@file` reads it from a file)
+    #[arg(long)]
+    pub down: Option<String>,
+
+    /// Files to include (e.g., "*.rs" "README.md"); empty means all text files
+    #[arg(long)]
+    pub files: Vec<String>,
+
+    /// Include all text files, ignoring include patterns
+    #[arg(long)]
+    pub all: bool,
+
+    /// Fail if an include pattern matches no files
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Emit only the project tree, without file contents
+    #[arg(long)]
+    pub tree_only: bool,
+
+    /// List every file the walker sees in the tree, marking the embedded ones with `*` (overrides [tree] full)
+    #[arg(long)]
+    pub full_tree: bool,
+
+    /// Annotate the tree with sizes, line counts, token estimates and directory totals
+    #[arg(long)]
+    pub annotate: bool,
+
+    /// Show this many tree levels; deeper directories collapse into "(N files, X KiB)"
+    #[arg(long, value_name = "N")]
+    pub depth: Option<usize>,
+
+    /// Do not descend deeper than this many directory levels (overrides [walk] max_depth)
+    #[arg(long)]
+    pub max_depth: Option<usize>,
+
+    /// Fail if more than this many files are selected (overrides [walk] max_files)
+    #[arg(long)]
+    pub max_files: Option<usize>,
+
+    /// Fail if the selected files exceed this many bytes in total (overrides [walk] max_total_bytes)
+    #[arg(long)]
+    pub max_total_bytes: Option<u64>,
+
+    /// Worker threads for walking and reading files (0 = number of CPUs, 1 = sequential)
+    #[arg(short, long, default_value_t = 0)]
+    pub jobs: usize,
+
+    /// Keep this many previous snapshots as OUTPUT.bak, OUTPUT.bak.1, ... (overrides [output] backups)
+    #[arg(long)]
+    pub backups: Option<usize>,
+
+    /// Overwrite the output even if it was not generated by ginpee
+    #[arg(long)]
+    pub force: bool,
+
+    /// Re-read and re-process every file instead of reusing .ginpee/cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Use include/exclude patterns from [profiles.NAME] instead of [files]
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Path to config file (default: ginpee.toml)
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Template file used instead of the built-in layout
+    #[arg(long)]
+    pub template: Option<PathBuf>,
+
+    /// Print diagnostic details (e.g. where the configuration came from)
+    #[arg(short, long)]
+    pub verbose: bool,
+}
+
 pub fn run_command(
-    output: std::path::PathBuf,
+    output: PathBuf,
     top: Option<String>,
     down: Option<String>,
     files: Vec<String>,
-    config_path: std::path::PathBuf,
+    config_path: PathBuf,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let config = config::load(&config_path).unwrap_or_default();
-
-    let top = top.or_else(|| config.top.map(|c| c.text));
-    let down = down.or_else(|| config.down.map(|c| c.text));
-    let files = if !files.is_empty() {
-        files
-    } else {
-        config.files.map(|f| f.include).unwrap_or_default()
-    };
+    run(RunArgs {
+        output: Some(output),
+        top,
+        down,
+        files,
+        config: Some(config_path),
+        ..RunArgs::default()
+    })
+}
+
+/// Настройки обхода из конфига (без флагов командной строки). Их же использует подсчёт совпадений
+/// в `ginpee add`, чтобы он показывал то же, что потом соберёт `run`.
+pub(crate) fn collect_options(
+    config: &config::Config,
+    selection: &config::Files,
+    output: &Path,
+    backups: usize,
+) -> collector::CollectOptions {
+    let walk = config.walk.clone().unwrap_or_default();
+    collector::CollectOptions {
+        matching: selection.match_options(),
+        exclude: selection.exclude.clone(),
+        skip_paths: std::iter::once(output.to_path_buf())
+            .chain(writer::related_paths(output, Path::new(cache::GINPEE_DIR)))
+            .chain(writer::backup_paths(output, backups))
+            .collect(),
+        hidden: walk.hidden,
+        follow_symlinks: walk.follow_symlinks,
+        limits: collector::Limits {
+            max_depth: walk.max_depth,
+            max_files: walk.max_files,
+            max_total_bytes: walk.max_total_bytes,
+        },
+        ..collector::CollectOptions::default()
+    }
+}
+
+pub fn run(args: RunArgs) -> Result<(), Box<dyn std::error::Error>> {
+    // Отсутствующий конфиг — не ошибка, а вот битый конфиг молча игнорировать нельзя
+    let (config, source) = config::resolve(args.config.as_deref())?;
+    if args.verbose {
+        eprintln!("config: {}", source);
+    }
+    let config_dir = config::base_dir(config::path(args.config.as_deref()));
 
+    let top = match args.top {
+        Some(text) => Some(config::resolve_cli_text(&text)?),
+        None => config.top.as_ref().map(|c| c.resolve(&config_dir)).transpose()?,
+    };
+    let down = match args.down {
+        Some(text) => Some(config::resolve_cli_text(&text)?),
+        None => config.down.as_ref().map(|c| c.resolve(&config_dir)).transpose()?,
+    };
+    let mut selection = config.selection(args.profile.as_deref())?;
+    if !args.files.is_empty() {
+        selection.include = args.files;
+    }
+    let output = args
+        .output
+        .or_else(|| config.output.as_ref().and_then(|o| o.path.clone()))
+        .unwrap_or_else(|| PathBuf::from("project.md"));
+    let backups = args
+        .backups
+        .or_else(|| config.output.as_ref().and_then(|o| o.backups))
+        .unwrap_or(0);
     let base_path = std::env::current_dir()?;
-    let collected_files = collector::collect_files(&base_path, &files, ".gpskip")?;
+    let state_dir = base_path.join(cache::GINPEE_DIR);
+    writer::check_overwrite(&output, args.force, &state_dir)?;
+    // Шаблон из CLI ищется от текущей директории, из конфига — от директории конфига
+    let template = args.template.or_else(|| {
+        config
+            .output
+            .as_ref()
+            .and_then(|o| o.template.clone())
+            .map(|t| config_dir.join(t))
+    });
 
-    let (tree, contents) = formatter::build_tree_and_content(&collected_files, &base_path)?;
+    let match_options = selection.match_options();
+    let modes = Arc::new(mode::Modes::new(&selection.modes, match_options)?);
+    let mut options = collect_options(&config, &selection, &output, backups);
+    options.limits = collector::Limits {
+        max_depth: args.max_depth.or(options.limits.max_depth),
+        max_files: args.max_files.or(options.limits.max_files),
+        max_total_bytes: args.max_total_bytes.or(options.limits.max_total_bytes),
+    };
+    options.all = args.all;
+    options.strict = args.strict;
+    options.jobs = args.jobs;
+    options.modes = Some(Arc::clone(&modes));
+    let collection = collector::collect(&base_path, &selection.include, &options)?;
+    for warning in &collection.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    if args.verbose {
+        for (pattern, count) in &collection.pattern_matches {
+            eprintln!("pattern `{}`: {} files", pattern, count);
+        }
+    }
+    let collected_files = collection.files;
+
+    let tree_config = config.tree.clone().unwrap_or_default();
+    let tree_options = tree::Options {
+        annotate: args.annotate,
+        depth: args.depth,
+        max_entries: tree_config.max_entries,
+        collapse: tree::dir_patterns(&tree_config.collapse, "collapse", match_options)?,
+        expand: tree::dir_patterns(&tree_config.expand, "expand", match_options)?,
+    };
+    let full_tree = args.full_tree || tree_config.full;
+    let context = if full_tree { collector::walk_all(&base_path, &options) } else { Vec::new() };
+    let layout_options = formatter::LayoutOptions {
+        context: &context,
+        tree: tree_options,
+        modes: Some(&modes),
+    };
+    let mut layout = formatter::layout_with(&collected_files, &collection.links, &base_path, &layout_options)?;
+    let cache = (!args.no_cache).then(|| Arc::new(Cache::new(base_path.join(cache::CACHE_DIR))));
+    if let Some(cache) = &cache {
+        layout = layout.with_cache(Arc::clone(cache));
+    }
 
-    writer::write_project_md(&output, top, &tree, &contents, down)?;
+    let mut vars = vars::builtin(&base_path, layout.entries.len());
+    if args.tree_only {
+        layout.entries.clear();
+    }
+    vars.extend(config.vars);
+    let top = top.map(|text| vars::substitute(&text, &vars));
+    let down = down.map(|text| vars::substitute(&text, &vars));
+
+    let template_source = template
+        .map(|path| {
+            std::fs::read_to_string(&path)
+                .map_err(|e| format!("failed to read template {}: {}", path.display(), e))
+        })
+        .transpose()?;
+
+    writer::backup(&output, backups)?;
+    match template_source {
+        Some(source) => writer::write_layout_with_template(&output, &source, &vars, top, &layout, down)?,
+        None => writer::write_layout(&output, top, &layout, down, args.jobs)?,
+    }
+    writer::remember(&state_dir, &output)?;
+    if let Some(cache) = &cache {
+        let pruned = cache.prune();
+        if args.verbose {
+            let (reused, processed) = cache.stats();
+            eprintln!("cache: {} files reused, {} processed, {} stale records removed", reused, processed, pruned);
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
@@ -6,7 +6,7 @@
 //! ginpee init
 //! ginpee run --output docs.md
 //! ```
-use ginpee::{Commands, run_command};
+use ginpee::{CacheCommand, Commands, ConfigCommand};
 
 use clap::Parser;
 
@@ -21,13 +21,37 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Init { force } => {
-            ginpee::init::run(force)?;
+        Commands::Init { force, update, presets, list_presets } => {
+            if list_presets {
+                ginpee::init::list_presets();
+            } else {
+                ginpee::init::run_with(&ginpee::init::InitOptions { force, update, presets })?;
+            }
         }
-        Commands::Run { output, top, down, files, config } => {
-            run_command(output, top, down, files, config)?;
+        Commands::Run(args) => {
+            ginpee::run(args)?;
+        }
+        Commands::Add(args) => {
+            ginpee::edit::run_add(args.config.as_deref(), args.target(), &args.patterns)?;
+        }
+        Commands::Remove(args) => {
+            ginpee::edit::run_remove(args.config.as_deref(), args.target(), &args.patterns)?;
+        }
+        Commands::Pick { config } => {
+            ginpee::pick::run(config.as_deref())?;
+        }
+        Commands::Config { command: ConfigCommand::Show { config, verbose } } => {
+            print!("{}", ginpee::config::show(config.as_deref(), verbose)?);
+        }
+        Commands::Cache { command: CacheCommand::Clear } => {
+            let dir = std::env::current_dir()?.join(ginpee::cache::CACHE_DIR);
+            if ginpee::cache::clear(&dir)? {
+                println!("Removed {}", ginpee::cache::CACHE_DIR);
+            } else {
+                println!("Cache is already empty");
+            }
         }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
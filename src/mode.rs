@@ -0,0 +1,219 @@
+//! Модуль режимов встраивания: полностью, структура, начало / конец файла или только имя.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::collector::{MatchOptions, PatternSet};
+use crate::{formatter, outline};
+
+/// Как файл попадает в результат.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Mode {
+    /// Содержимое целиком
+    #[default]
+    Full,
+    /// Только объявления (сигнатуры функций, типы), тела опущены
+    Outline,
+    /// Первые N строк
+    Head(usize),
+    /// Последние N строк
+    Tail(usize),
+    /// Только строка в дереве, без содержимого
+    NameOnly,
+}
+
+impl Mode {
+    /// Преобразует содержимое файла `path` согласно режиму.
+    ///
+    /// Для `outline` без поддержки языка (или при ошибке разбора) файл остаётся целиком.
+    pub fn apply(self, path: &str, content: String) -> String {
+        match self {
+            Mode::Full | Mode::NameOnly => content,
+            Mode::Outline => outline::outline(formatter::language(path), &content).unwrap_or(content),
+            Mode::Head(count) => {
+                let total = content.lines().count();
+                if total <= count {
+                    return content;
+                }
+                let mut head: Vec<&str> = content.lines().take(count).collect();
+                let note = format!("… ({} more lines)", total - count);
+                head.push(&note);
+                head.join("\n")
+            }
+            Mode::Tail(count) => {
+                let total = content.lines().count();
+                if total <= count {
+                    return content;
+                }
+                let note = format!("… ({} lines above)", total - count);
+                let mut tail = vec![note.as_str()];
+                tail.extend(content.lines().skip(total - count));
+                tail.join("\n")
+            }
+        }
+    }
+}
+
+impl Mode {
+    /// Сколько байт файла попадёт в результат (для `max_total_bytes`).
+    ///
+    /// `name-only` не встраивается вовсе, у `head:N` считаются первые N строк; для `outline`
+    /// и `tail:N` это неизвестно без обработки, поэтому берётся размер всего файла как верхняя оценка.
+    pub fn embedded_size(self, path: &Path, size: u64) -> u64 {
+        match self {
+            Mode::NameOnly => 0,
+            Mode::Head(count) => {
+                let Ok(file) = File::open(path) else {
+                    return size;
+                };
+                BufReader::new(file)
+                    .split(b'\n')
+                    .take(count)
+                    .map_while(Result::ok)
+                    .map(|line| line.len() as u64 + 1)
+                    .sum::<u64>()
+                    .min(size)
+            }
+            Mode::Full | Mode::Outline | Mode::Tail(_) => size,
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Full => write!(f, "full"),
+            Mode::Outline => write!(f, "outline"),
+            Mode::Head(count) => write!(f, "head:{}", count),
+            Mode::Tail(count) => write!(f, "tail:{}", count),
+            Mode::NameOnly => write!(f, "name-only"),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = |count: &str| {
+            count
+                .parse::<usize>()
+                .map_err(|_| format!("invalid line count in mode `{}`", s))
+        };
+        match s.split_once(':') {
+            None => match s {
+                "full" => Ok(Mode::Full),
+                "outline" => Ok(Mode::Outline),
+                "name-only" => Ok(Mode::NameOnly),
+                _ => Err(format!(
+                    "unknown mode `{}` (expected full, outline, head:N, tail:N or name-only)",
+                    s
+                )),
+            },
+            Some(("head", count)) => Ok(Mode::Head(lines(count)?)),
+            Some(("tail", count)) => Ok(Mode::Tail(lines(count)?)),
+            Some(_) => Err(format!("unknown mode `{}` (expected head:N or tail:N)", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Mode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Mode> for String {
+    fn from(mode: Mode) -> Self {
+        mode.to_string()
+    }
+}
+
+/// Правило из `[files] modes`: `{ glob = "src/**", mode = "outline" }`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Rule {
+    pub glob: String,
+    pub mode: Mode,
+}
+
+/// Набор правил; решает первое совпавшее, файлы без совпадений встраиваются целиком.
+#[derive(Debug)]
+pub struct Modes {
+    patterns: PatternSet,
+    modes: Vec<Mode>,
+}
+
+impl Modes {
+    pub fn new(rules: &[Rule], options: MatchOptions) -> Result<Self, Box<dyn std::error::Error>> {
+        let globs: Vec<String> = rules.iter().map(|rule| rule.glob.clone()).collect();
+        Ok(Self {
+            patterns: PatternSet::with_options(&globs, options)
+                .map_err(|e| format!("[files] modes: {}", e))?,
+            modes: rules.iter().map(|rule| rule.mode).collect(),
+        })
+    }
+
+    pub fn for_path(&self, relative_path: &Path) -> Mode {
+        self.patterns
+            .matching(relative_path)
+            .first()
+            .map_or(Mode::Full, |&index| self.modes[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_apply() {
+        assert_eq!("head:2".parse::<Mode>().unwrap(), Mode::Head(2));
+        assert_eq!("name-only".parse::<Mode>().unwrap(), Mode::NameOnly);
+        assert!("head:x".parse::<Mode>().is_err());
+        assert!("body".parse::<Mode>().is_err());
+
+        let content = "a\nb\nc\nd".to_string();
+        assert_eq!(Mode::Head(2).apply("a.txt", content.clone()), "a\nb\n… (2 more lines)");
+        assert_eq!(Mode::Tail(1).apply("a.txt", content.clone()), "… (3 lines above)\nd");
+        assert_eq!(Mode::Head(10).apply("a.txt", content.clone()), content);
+    }
+
+    #[test]
+    fn test_embedded_size() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.txt");
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+        assert_eq!(Mode::Full.embedded_size(&path, 14), 14);
+        assert_eq!(Mode::Head(2).embedded_size(&path, 14), 8);
+        assert_eq!(Mode::Head(10).embedded_size(&path, 14), 14);
+        assert_eq!(Mode::NameOnly.embedded_size(&path, 14), 0);
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules: Vec<Rule> = toml::from_str::<toml::Table>(
+            r#"modes = [
+                { glob = "src/handlers/**", mode = "full" },
+                { glob = "src/**", mode = "outline" },
+                { glob = "tests/**", mode = "name-only" },
+            ]"#,
+        )
+        .unwrap()["modes"]
+            .clone()
+            .try_into()
+            .unwrap();
+        let modes = Modes::new(&rules, MatchOptions::default()).unwrap();
+
+        assert_eq!(modes.for_path(Path::new("src/handlers/user.rs")), Mode::Full);
+        assert_eq!(modes.for_path(Path::new("src/db.rs")), Mode::Outline);
+        assert_eq!(modes.for_path(Path::new("tests/api.rs")), Mode::NameOnly);
+        assert_eq!(modes.for_path(Path::new("README.md")), Mode::Full);
+    }
+}
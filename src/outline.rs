@@ -0,0 +1,337 @@
+//! Модуль извлечения структуры файла для режима `outline`.
+//!
+//! Файл разбирается tree-sitter, тела функций и методов заменяются на `{ … }`
+//! (в Python — на `…`), всё остальное (сигнатуры, типы, трейты, impl, модули) остаётся.
+//! Каждая строка помечается номером строки в исходном файле.
+//!
+//! Nix — исключение: грамматики нет, и его outline строит эвристический сканер скобок (`nix_bodies`).
+use tree_sitter::{Language, Node, Parser};
+
+/// Грамматика и узлы, тела которых опускаются.
+struct Grammar {
+    language: Language,
+    functions: &'static [&'static str],
+    placeholder: &'static str,
+}
+
+/// Узлы-тела, которые можно заменить: у стрелочных функций тело бывает выражением, его оставляем.
+const BODIES: &[&str] = &["block", "statement_block", "compound_statement"];
+
+const JS_FUNCTIONS: &[&str] = &[
+    "function_declaration",
+    "function_expression",
+    "generator_function_declaration",
+    "generator_function",
+    "method_definition",
+    "arrow_function",
+];
+
+fn grammar(lang: &str) -> Option<Grammar> {
+    let (language, functions, placeholder): (Language, &'static [&'static str], _) = match lang {
+        "rust" => (tree_sitter_rust::LANGUAGE.into(), &["function_item"], "{ … }"),
+        "python" => (tree_sitter_python::LANGUAGE.into(), &["function_definition"], "…"),
+        "javascript" | "jsx" => (tree_sitter_javascript::LANGUAGE.into(), JS_FUNCTIONS, "{ … }"),
+        "typescript" => (tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(), JS_FUNCTIONS, "{ … }"),
+        "tsx" => (tree_sitter_typescript::LANGUAGE_TSX.into(), JS_FUNCTIONS, "{ … }"),
+        "go" => (
+            tree_sitter_go::LANGUAGE.into(),
+            &["function_declaration", "method_declaration", "func_literal"],
+            "{ … }",
+        ),
+        "c" => (tree_sitter_c::LANGUAGE.into(), &["function_definition"], "{ … }"),
+        "cpp" => (
+            tree_sitter_cpp::LANGUAGE.into(),
+            &["function_definition", "lambda_expression"],
+            "{ … }",
+        ),
+        _ => return None,
+    };
+    Some(Grammar { language, functions, placeholder })
+}
+
+/// Есть ли извлечение структуры для языка (см. [`crate::formatter::language`]); для Nix — эвристическое.
+pub fn supported(lang: &str) -> bool {
+    lang == "nix" || grammar(lang).is_some()
+}
+
+/// Структура файла; `None`, если язык не поддерживается или файл не разобрался —
+/// тогда файл встраивается целиком.
+pub fn outline(lang: &str, content: &str) -> Option<String> {
+    if lang == "nix" {
+        return nix_bodies(content).map(|bodies| render(content, &bodies, "{ … }"));
+    }
+    let grammar = grammar(lang)?;
+    let mut parser = Parser::new();
+    parser.set_language(&grammar.language).ok()?;
+    let tree = parser.parse(content, None)?;
+    let root = tree.root_node();
+    // Частично разобранный файл дал бы обрывки вместо структуры
+    if root.has_error() {
+        return None;
+    }
+    let mut bodies = Vec::new();
+    collect_bodies(root, grammar.functions, &mut bodies);
+    Some(render(content, &bodies, grammar.placeholder))
+}
+
+fn collect_bodies(node: Node, functions: &[&str], bodies: &mut Vec<(usize, usize)>) {
+    if functions.contains(&node.kind())
+        && let Some(body) = node.child_by_field_name("body")
+        && BODIES.contains(&body.kind())
+    {
+        bodies.push((body.start_byte(), body.end_byte()));
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_bodies(child, functions, bodies);
+    }
+}
+
+/// В Nix нет деклараций как таковых: сворачиваем многострочные `{ ... }`, открытые внутри
+/// тела модуля (и значения `let` верхнего уровня), так что остаются аргументы модуля и его атрибуты.
+///
+/// Готовой грамматики Nix для tree-sitter среди зависимостей нет, поэтому это сканер, который знает
+/// строки (`"..."`, `''...''` с `${...}` внутри), комментарии и `let ... in`, а скобки просто считает.
+/// Незакрытая строка, комментарий или скобка — `None`: файл встраивается целиком.
+fn nix_bodies(content: &str) -> Option<Vec<(usize, usize)>> {
+    let bytes = content.as_bytes();
+    let mut bodies = Vec::new();
+    let mut depth = 0usize;
+    // Начало сворачиваемой скобки и её глубина
+    let mut open: Option<(usize, usize)> = None;
+    let mut in_let = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'#' => i = skip_line(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = skip_block_comment(content, i)?,
+            b'"' => i = skip_string(bytes, i)?,
+            b'\'' if bytes.get(i + 1) == Some(&b'\'') => i = skip_indented_string(bytes, i)?,
+            b'{' => {
+                if open.is_none() && (depth == 1 || (depth == 0 && in_let)) {
+                    open = Some((i, depth));
+                }
+                depth += 1;
+                i += 1;
+            }
+            b'}' => {
+                depth = depth.checked_sub(1)?;
+                if let Some((start, level)) = open
+                    && level == depth
+                {
+                    if content[start..i].contains('\n') {
+                        bodies.push((start, i + 1));
+                    }
+                    open = None;
+                }
+                i += 1;
+            }
+            byte if byte.is_ascii_alphabetic() || byte == b'_' => {
+                let word_end = bytes[i..]
+                    .iter()
+                    .position(|&b| !(b.is_ascii_alphanumeric() || matches!(b, b'_' | b'\'' | b'-')))
+                    .map_or(bytes.len(), |end| i + end);
+                if depth == 0 {
+                    match &content[i..word_end] {
+                        "let" => in_let = true,
+                        "in" => in_let = false,
+                        _ => {}
+                    }
+                }
+                i = word_end;
+            }
+            _ => i += 1,
+        }
+    }
+    (depth == 0).then_some(bodies)
+}
+
+fn skip_line(bytes: &[u8], i: usize) -> usize {
+    bytes[i..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |end| i + end)
+}
+
+fn skip_block_comment(content: &str, i: usize) -> Option<usize> {
+    content[i + 2..].find("*/").map(|end| i + 2 + end + 2)
+}
+
+/// `"..."`: позиция после закрывающей кавычки.
+fn skip_string(bytes: &[u8], i: usize) -> Option<usize> {
+    let mut j = i + 1;
+    loop {
+        match *bytes.get(j)? {
+            b'\\' => j += 2,
+            b'"' => return Some(j + 1),
+            b'$' if bytes.get(j + 1) == Some(&b'{') => j = skip_interpolation(bytes, j + 2)?,
+            _ => j += 1,
+        }
+    }
+}
+
+/// `''...''`: позиция после закрывающих кавычек; `'''`, `''$` и `''\x` — экранирование, а не конец.
+fn skip_indented_string(bytes: &[u8], i: usize) -> Option<usize> {
+    let mut j = i + 2;
+    loop {
+        match *bytes.get(j)? {
+            b'\'' if bytes.get(j + 1) == Some(&b'\'') => match bytes.get(j + 2) {
+                Some(b'\'' | b'$') => j += 3,
+                Some(b'\\') => j += 4,
+                _ => return Some(j + 2),
+            },
+            b'$' if bytes.get(j + 1) == Some(&b'{') => j = skip_interpolation(bytes, j + 2)?,
+            _ => j += 1,
+        }
+    }
+}
+
+/// Выражение внутри `${...}` (`i` — сразу после `${`): позиция после закрывающей скобки.
+fn skip_interpolation(bytes: &[u8], i: usize) -> Option<usize> {
+    let mut depth = 1;
+    let mut j = i;
+    loop {
+        match *bytes.get(j)? {
+            b'"' => j = skip_string(bytes, j)?,
+            b'\'' if bytes.get(j + 1) == Some(&b'\'') => j = skip_indented_string(bytes, j)?,
+            b'{' => {
+                depth += 1;
+                j += 1;
+            }
+            b'}' => {
+                depth -= 1;
+                j += 1;
+                if depth == 0 {
+                    return Some(j);
+                }
+            }
+            _ => j += 1,
+        }
+    }
+}
+
+/// Заменяет диапазоны на `placeholder` и нумерует строки по исходному файлу.
+fn render(content: &str, bodies: &[(usize, usize)], placeholder: &str) -> String {
+    let mut out = Numbered { lines: Vec::new(), current: String::new(), line: 1, source_line: 1 };
+    let mut position = 0;
+    for &(start, end) in bodies {
+        out.push(&content[position..start]);
+        out.current.push_str(placeholder);
+        out.source_line += content[start..end].matches('\n').count();
+        position = end;
+    }
+    out.push(&content[position..]);
+    if !out.current.is_empty() {
+        out.lines.push((out.line, out.current));
+    }
+
+    let width = out.lines.last().map_or(1, |(number, _)| number.to_string().len());
+    out.lines
+        .iter()
+        .map(|(number, text)| format!("{:>width$}  {}", number, text).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Строки результата с номерами исходных строк, на которых они начинаются.
+struct Numbered {
+    lines: Vec<(usize, String)>,
+    current: String,
+    line: usize,
+    source_line: usize,
+}
+
+impl Numbered {
+    fn push(&mut self, text: &str) {
+        for (index, part) in text.split('\n').enumerate() {
+            if index > 0 {
+                self.lines.push((self.line, std::mem::take(&mut self.current)));
+                self.source_line += 1;
+                self.line = self.source_line;
+            }
+            self.current.push_str(part);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_outline_keeps_line_numbers() {
+        let source = "\
+use std::fs;
+
+/// Reads the config.
+pub fn load(path: &str) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+";
+        let expected = [
+            " 1  use std::fs;",
+            " 2",
+            " 3  /// Reads the config.",
+            " 4  pub fn load(path: &str) -> String { … }",
+            " 7",
+            " 8  impl Config {",
+            " 9      pub fn new() -> Self { … }",
+            "12  }",
+        ];
+        assert_eq!(outline("rust", source).unwrap(), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_python_and_nix() {
+        let python = "class A:\n    def run(self):\n        return 1\n";
+        assert_eq!(outline("python", python).unwrap(), "1  class A:\n2      def run(self):\n3          …");
+
+        let nix = "{ pkgs, ... }:\n{\n  imports = [ ./a.nix ];\n  services.x = {\n    enable = true; # }\n  };\n}\n";
+        assert_eq!(
+            outline("nix", nix).unwrap(),
+            "1  { pkgs, ... }:\n2  {\n3    imports = [ ./a.nix ];\n4    services.x = { … };\n7  }"
+        );
+    }
+
+    #[test]
+    fn test_nix_strings_and_let() {
+        let nix = r#"let
+  names = {
+    a = "${lib.concat "}" x}";
+  };
+in {
+  script = ''
+    echo ''${HOME} {
+  '';
+  users = {
+    b = 1;
+  };
+}
+"#;
+        let expected = [
+            " 1  let",
+            " 2    names = { … };",
+            " 5  in {",
+            " 6    script = ''",
+            " 7      echo ''${HOME} {",
+            " 8    '';",
+            " 9    users = { … };",
+            "12  }",
+        ];
+        assert_eq!(outline("nix", nix).unwrap(), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_unsupported_or_broken_files_fall_back() {
+        assert!(outline("toml", "a = 1").is_none());
+        assert!(outline("rust", "fn broken( {").is_none());
+        assert!(outline("nix", "{\n  a = {\n    b = 1;\n}\n").is_none());
+        assert!(outline("nix", "{ a = \"unterminated; }").is_none());
+        assert!(!supported("markdown"));
+        assert!(supported("tsx"));
+    }
+}
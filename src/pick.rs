@@ -0,0 +1,437 @@
+//! Модуль интерактивного выбора файлов в терминале (`ginpee pick`).
+use ratatui::Terminal;
+use ratatui::backend::Backend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use crate::collector::{self, CollectOptions, Hidden, Matching};
+use crate::{RunArgs, config, edit, formatter};
+
+/// Чем закончился выбор.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Записать выбранные файлы в `[files] include`
+    Save,
+    /// Сразу сгенерировать `project.md` из выбранных файлов
+    Render,
+    /// Выйти без изменений
+    Quit,
+}
+
+#[derive(Debug)]
+struct Row {
+    /// Путь относительно корня проекта, через `/`
+    path: String,
+    depth: usize,
+    /// `None` для директорий
+    size: Option<u64>,
+}
+
+/// Состояние пикера; отрисовка и обработка клавиш не зависят от реального терминала.
+#[derive(Debug)]
+pub struct Picker {
+    rows: Vec<Row>,
+    checked: BTreeSet<usize>,
+    query: String,
+    searching: bool,
+    cursor: usize,
+}
+
+impl Picker {
+    /// `files` — пути файлов с размерами; `preselected` — уже включённые в конфиг.
+    pub fn new(files: Vec<(String, u64)>, preselected: &[String]) -> Self {
+        let mut files = files;
+        files.sort();
+
+        let mut rows: Vec<Row> = Vec::new();
+        let mut open_dirs: Vec<&str> = Vec::new();
+        for (path, size) in &files {
+            let components: Vec<&str> = path.split('/').collect();
+            let dirs = &components[..components.len() - 1];
+            let common = open_dirs.iter().zip(dirs).take_while(|(a, b)| a == b).count();
+            open_dirs.truncate(common);
+            for (depth, dir) in dirs.iter().enumerate().skip(common) {
+                open_dirs.push(dir);
+                rows.push(Row {
+                    path: components[..=depth].join("/"),
+                    depth,
+                    size: None,
+                });
+            }
+            rows.push(Row {
+                path: path.clone(),
+                depth: dirs.len(),
+                size: Some(*size),
+            });
+        }
+
+        let checked = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.size.is_some() && preselected.contains(&row.path))
+            .map(|(i, _)| i)
+            .collect();
+
+        Self {
+            rows,
+            checked,
+            query: String::new(),
+            searching: false,
+            cursor: 0,
+        }
+    }
+
+    /// Выбранные файлы в порядке дерева.
+    pub fn selection(&self) -> Vec<String> {
+        self.checked.iter().map(|&i| self.rows[i].path.clone()).collect()
+    }
+
+    /// Суммарный размер выбранных файлов в байтах.
+    pub fn selected_bytes(&self) -> u64 {
+        self.checked.iter().filter_map(|&i| self.rows[i].size).sum()
+    }
+
+    /// Индексы строк, видимых при текущем поиске (директории — если в них есть совпадения).
+    fn visible(&self) -> Vec<usize> {
+        if self.query.is_empty() {
+            return (0..self.rows.len()).collect();
+        }
+        let query = self.query.to_lowercase();
+        let matched: Vec<&str> = self
+            .rows
+            .iter()
+            .filter(|row| row.size.is_some() && row.path.to_lowercase().contains(&query))
+            .map(|row| row.path.as_str())
+            .collect();
+
+        (0..self.rows.len())
+            .filter(|&i| {
+                let row = &self.rows[i];
+                match row.size {
+                    Some(_) => matched.contains(&row.path.as_str()),
+                    None => matched.iter().any(|m| is_inside(m, &row.path)),
+                }
+            })
+            .collect()
+    }
+
+    /// Файлы, на которые действует строка: сам файл или всё видимое содержимое директории.
+    fn files_under(&self, index: usize, visible: &[usize]) -> Vec<usize> {
+        let row = &self.rows[index];
+        if row.size.is_some() {
+            return vec![index];
+        }
+        visible
+            .iter()
+            .copied()
+            .filter(|&i| self.rows[i].size.is_some() && is_inside(&self.rows[i].path, &row.path))
+            .collect()
+    }
+
+    fn toggle(&mut self, files: Vec<usize>) {
+        if files.iter().all(|i| self.checked.contains(i)) {
+            for i in files {
+                self.checked.remove(&i);
+            }
+        } else {
+            self.checked.extend(files);
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Outcome> {
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Outcome::Quit);
+        }
+
+        let visible = self.visible();
+        if self.searching {
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => self.searching = false,
+                KeyCode::Backspace => {
+                    self.query.pop();
+                }
+                KeyCode::Char(c) => self.query.push(c),
+                _ => {}
+            }
+            self.cursor = 0;
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.cursor = (self.cursor + 1).min(visible.len().saturating_sub(1));
+            }
+            KeyCode::Char(' ') => {
+                if let Some(&index) = visible.get(self.cursor) {
+                    let files = self.files_under(index, &visible);
+                    self.toggle(files);
+                }
+            }
+            KeyCode::Char('a') => {
+                let files = visible.iter().copied().filter(|&i| self.rows[i].size.is_some()).collect();
+                self.toggle(files);
+            }
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Char('s') => return Some(Outcome::Save),
+            KeyCode::Char('r') => return Some(Outcome::Render),
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Outcome::Quit),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let [search_area, list_area, footer_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(2),
+        ])
+        .areas(frame.area());
+
+        let search = if self.searching || !self.query.is_empty() {
+            format!("/{}{}", self.query, if self.searching { "_" } else { "" })
+        } else {
+            "Press / to search".to_string()
+        };
+        frame.render_widget(Paragraph::new(search), search_area);
+
+        let visible = self.visible();
+        let all: Vec<usize> = (0..self.rows.len()).collect();
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|&i| {
+                let row = &self.rows[i];
+                let name = row.path.rsplit('/').next().unwrap_or(&row.path);
+                let mark = match row.size {
+                    Some(_) if self.checked.contains(&i) => "[x]",
+                    Some(_) => "[ ]",
+                    None => {
+                        // Отметка директории учитывает все файлы, а не только найденные поиском
+                        let files = self.files_under(i, &all);
+                        let count = files.iter().filter(|f| self.checked.contains(f)).count();
+                        if count == 0 {
+                            "[ ]"
+                        } else if count == files.len() {
+                            "[x]"
+                        } else {
+                            "[~]"
+                        }
+                    }
+                };
+                let label = match row.size {
+                    Some(size) => format!("{} ({})", name, formatter::human_size(size)),
+                    None => format!("{}/", name),
+                };
+                ListItem::new(format!("{}{} {}", "  ".repeat(row.depth), mark, label))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::TOP | Borders::BOTTOM).title(" ginpee pick "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.cursor));
+        frame.render_stateful_widget(list, list_area, &mut state);
+
+        let bytes = self.selected_bytes();
+        let footer = vec![
+            Line::from(format!(
+                "{} files · {} · ~{} tokens",
+                self.checked.len(),
+                formatter::human_size(bytes),
+                formatter::estimate_tokens(bytes)
+            )),
+            Line::from("space: toggle  a: toggle all  /: search  s: save  r: render  q: quit"),
+        ];
+        frame.render_widget(Paragraph::new(footer), footer_area);
+    }
+
+    /// Цикл событий до выбора действия.
+    pub fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<Outcome, Box<dyn std::error::Error>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()?
+                && let Some(outcome) = self.handle_key(key)
+            {
+                return Ok(outcome);
+            }
+        }
+    }
+}
+
+fn is_inside(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Паттерн, который совпадает только с выбранным файлом.
+///
+/// Без привязки к корню (`./` в `glob`, `/` в `gitignore`) имя файла из корня совпало бы на любой глубине.
+fn saved_pattern(path: &str, matching: Matching) -> String {
+    match matching {
+        Matching::Glob => format!("./{}", glob::Pattern::escape(path)),
+        Matching::Gitignore => format!("/{}", globset::escape(path)),
+    }
+}
+
+/// `ginpee pick`: показывает дерево, затем сохраняет выбор в конфиг или сразу рендерит результат.
+pub fn run(explicit: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    if !std::io::stdout().is_terminal() || !std::io::stdin().is_terminal() {
+        return Err("ginpee pick needs an interactive terminal; use `ginpee add` to edit patterns from scripts".into());
+    }
+    let base_path = std::env::current_dir()?;
+    let (config, source) = config::resolve(explicit)?;
+    let selection = config.selection(None)?;
+    let matching = selection.match_options();
+
+    let to_relative = |path: &PathBuf| -> String {
+        path.strip_prefix(&base_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    };
+
+    let hidden = config.walk.as_ref().map(|w| w.hidden).unwrap_or_default();
+    // Выбор в пикере и есть явное указание, поэтому `explicit` показывает скрытые файлы
+    let listed = if hidden == Hidden::Explicit { Hidden::Include } else { hidden };
+    let follow_symlinks = config.walk.as_ref().is_some_and(|w| w.follow_symlinks);
+    let all = CollectOptions { all: true, hidden: listed, follow_symlinks, ..CollectOptions::default() };
+    let files: Vec<(String, u64)> = collector::collect(&base_path, &[], &all)?
+        .files
+        .iter()
+        .map(|path| (to_relative(path), fs::metadata(path).map(|m| m.len()).unwrap_or(0)))
+        .collect();
+
+    let preselected: Vec<String> = if selection.include.is_empty() {
+        Vec::new()
+    } else {
+        let options = CollectOptions {
+            exclude: selection.exclude,
+            matching,
+            hidden,
+            follow_symlinks,
+            ..CollectOptions::default()
+        };
+        collector::collect(&base_path, &selection.include, &options)?
+            .files
+            .iter()
+            .map(to_relative)
+            .collect()
+    };
+
+    let mut picker = Picker::new(files, &preselected);
+    let mut terminal = ratatui::init();
+    let outcome = picker.run(&mut terminal);
+    ratatui::restore();
+
+    let patterns: Vec<String> = picker
+        .selection()
+        .iter()
+        .map(|path| saved_pattern(path, matching.matching))
+        .collect();
+
+    match outcome? {
+        Outcome::Quit => println!("Nothing changed."),
+        Outcome::Save => {
+            // Манифест, из которого взят конфиг, правится на месте — как в `ginpee add`
+            let (config_path, table) = config::edit_location(&source, explicit)?;
+            let content = fs::read_to_string(&config_path).unwrap_or_default();
+            let target = edit::Target { exclude: false, profile: None, table };
+            fs::write(&config_path, edit::set_patterns(&content, target, &patterns)?)?;
+            println!("Saved {} files to {}", patterns.len(), config_path.display());
+        }
+        Outcome::Render => {
+            if patterns.is_empty() {
+                return Err("no files selected".into());
+            }
+            crate::run(RunArgs {
+                files: patterns,
+                config: explicit.map(Path::to_path_buf),
+                ..RunArgs::default()
+            })?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn picker() -> Picker {
+        Picker::new(
+            vec![
+                ("src/main.rs".to_string(), 400),
+                ("src/lib.rs".to_string(), 800),
+                ("README.md".to_string(), 100),
+            ],
+            &["README.md".to_string()],
+        )
+    }
+
+    fn screen(picker: &Picker) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(70, 10)).unwrap();
+        terminal.draw(|frame| picker.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_toggle_directory_and_totals() {
+        let mut picker = picker();
+        // README.md, src/, src/lib.rs, src/main.rs
+        picker.handle_key(key(KeyCode::Down));
+        picker.handle_key(key(KeyCode::Char(' ')));
+
+        assert_eq!(picker.selection(), vec!["README.md", "src/lib.rs", "src/main.rs"]);
+        let screen = screen(&picker);
+        assert!(screen.contains("[x] src/"));
+        assert!(screen.contains("3 files · 1.3 KiB · ~325 tokens"));
+    }
+
+    #[test]
+    fn test_saved_patterns_match_only_picked_files() {
+        for matching in [Matching::Glob, Matching::Gitignore] {
+            let options = collector::MatchOptions { matching, case_insensitive: false };
+            let patterns = collector::PatternSet::with_options(&[saved_pattern("README.md", matching)], options).unwrap();
+            assert!(patterns.find(Path::new("README.md")).is_some(), "{:?}", matching);
+            assert!(patterns.find(Path::new("docs/README.md")).is_none(), "{:?}", matching);
+        }
+    }
+
+    #[test]
+    fn test_search_filters_tree() {
+        let mut picker = picker();
+        picker.handle_key(key(KeyCode::Char('/')));
+        for c in "main".chars() {
+            picker.handle_key(key(KeyCode::Char(c)));
+        }
+        picker.handle_key(key(KeyCode::Enter));
+        picker.handle_key(key(KeyCode::Char('a')));
+
+        let screen = screen(&picker);
+        assert!(screen.contains("/main"));
+        assert!(screen.contains("[~] src/"));
+        assert!(!screen.contains("lib.rs"));
+        assert_eq!(picker.handle_key(key(KeyCode::Char('s'))), Some(Outcome::Save));
+        assert_eq!(picker.selection(), vec!["README.md", "src/main.rs"]);
+    }
+}
@@ -0,0 +1,185 @@
+//! Модуль пресетов для `ginpee init`: определение экосистемы проекта по маркерным файлам.
+use std::path::Path;
+
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Файлы в корне проекта, по которым пресет определяется автоматически
+    pub markers: &'static [&'static str],
+    /// Паттерны для `[files] include`
+    pub include: &'static [&'static str],
+    /// Строки для `.gpskip`
+    pub skip: &'static [&'static str],
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "rust",
+        description: "Rust (Cargo)",
+        markers: &["Cargo.toml"],
+        include: &["*.rs", "Cargo.toml", "build.rs", "rust-toolchain.toml"],
+        skip: &["target/"],
+    },
+    Preset {
+        name: "node",
+        description: "JavaScript / TypeScript (npm, yarn, pnpm)",
+        markers: &["package.json"],
+        include: &["*.js", "*.jsx", "*.ts", "*.tsx", "*.mjs", "*.cjs", "package.json", "tsconfig.json"],
+        skip: &["node_modules/", "dist/", "build/", "coverage/", ".next/", ".turbo/"],
+    },
+    Preset {
+        name: "python",
+        description: "Python (pyproject, setuptools, pip)",
+        markers: &["pyproject.toml", "setup.py", "setup.cfg", "requirements.txt"],
+        include: &["*.py", "pyproject.toml", "setup.py", "setup.cfg", "requirements*.txt"],
+        skip: &["__pycache__/", "*.pyc", ".venv/", "venv/", ".pytest_cache/", ".mypy_cache/", ".tox/", "*.egg-info/"],
+    },
+    Preset {
+        name: "go",
+        description: "Go modules",
+        markers: &["go.mod"],
+        include: &["*.go", "go.mod"],
+        skip: &["vendor/"],
+    },
+    Preset {
+        name: "nix",
+        description: "Nix flakes and expressions",
+        markers: &["flake.nix", "default.nix", "shell.nix"],
+        include: &["*.nix"],
+        skip: &["result", "result-*"],
+    },
+    Preset {
+        name: "cmake",
+        description: "C / C++ with CMake",
+        markers: &["CMakeLists.txt"],
+        include: &["*.c", "*.h", "*.cpp", "*.hpp", "*.cc", "CMakeLists.txt", "*.cmake"],
+        skip: &["build/", "cmake-build-*/"],
+    },
+    Preset {
+        name: "make",
+        description: "C / C++ with Make",
+        markers: &["Makefile", "makefile", "GNUmakefile"],
+        include: &["*.c", "*.h", "*.cpp", "*.hpp", "Makefile", "*.mk"],
+        skip: &["*.o", "*.a", "*.so"],
+    },
+    Preset {
+        name: "java",
+        description: "Java / Kotlin (Maven, Gradle)",
+        markers: &["pom.xml", "build.gradle", "build.gradle.kts"],
+        include: &["*.java", "*.kt", "pom.xml", "*.gradle", "*.gradle.kts"],
+        skip: &["target/", "build/", ".gradle/"],
+    },
+    Preset {
+        name: "ruby",
+        description: "Ruby (Bundler)",
+        markers: &["Gemfile"],
+        include: &["*.rb", "Gemfile", "*.gemspec", "Rakefile"],
+        skip: &["vendor/bundle/", ".bundle/"],
+    },
+    Preset {
+        name: "php",
+        description: "PHP (Composer)",
+        markers: &["composer.json"],
+        include: &["*.php", "composer.json"],
+        skip: &["vendor/"],
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|p| p.name == name)
+}
+
+/// Исходники C/C++, без которых `Makefile` не делает проект C-проектом.
+const MAKE_SOURCES: &[&str] = &["c", "h", "cc", "cpp", "hpp"];
+
+/// Все пресеты, чьи маркеры есть в `dir` (для полиглотных репозиториев — несколько).
+///
+/// `Makefile` часто лишь запускает задачи, поэтому `make` выбирается, только если в корне или в `src/`
+/// есть исходники C/C++ и не нашлось пресета конкретного языка (`nix` языком не считается).
+pub fn detect(dir: &Path) -> Vec<&'static Preset> {
+    let mut found: Vec<_> = PRESETS
+        .iter()
+        .filter(|p| p.name != "make" && has_marker(p, dir))
+        .collect();
+    if let Some(make) = find("make")
+        && has_marker(make, dir)
+        && found.iter().all(|p| p.name == "nix")
+        && [dir.to_path_buf(), dir.join("src")].iter().any(|d| has_sources(d, MAKE_SOURCES))
+    {
+        found.push(make);
+    }
+    found
+}
+
+fn has_marker(preset: &Preset, dir: &Path) -> bool {
+    preset.markers.iter().any(|m| dir.join(m).is_file())
+}
+
+fn has_sources(dir: &Path, extensions: &[&str]) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let path = entry.path();
+        path.is_file()
+            && path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| extensions.contains(&e))
+    })
+}
+
+/// Объединение списков пресетов без повторов, в порядке появления.
+pub fn merged<'a>(presets: &[&'a Preset], field: fn(&'a Preset) -> &'a [&'a str]) -> Vec<&'a str> {
+    let mut result: Vec<&str> = Vec::new();
+    for preset in presets {
+        for item in field(preset) {
+            if !result.contains(item) {
+                result.push(item);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_polyglot() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("Cargo.toml"), "[package]").unwrap();
+        fs::write(temp_dir.path().join("flake.nix"), "{}").unwrap();
+        fs::write(temp_dir.path().join("Makefile"), "all:").unwrap();
+
+        let names: Vec<_> = detect(temp_dir.path()).iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["rust", "nix"]);
+    }
+
+    #[test]
+    fn test_detect_make_needs_c_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        let names = |dir: &Path| detect(dir).iter().map(|p| p.name).collect::<Vec<_>>();
+        fs::write(temp_dir.path().join("Makefile"), "all:").unwrap();
+        assert!(names(temp_dir.path()).is_empty());
+
+        fs::create_dir(temp_dir.path().join("src")).unwrap();
+        fs::write(temp_dir.path().join("src/main.c"), "int main() {}").unwrap();
+        fs::write(temp_dir.path().join("flake.nix"), "{}").unwrap();
+        assert_eq!(names(temp_dir.path()), vec!["nix", "make"]);
+
+        fs::write(temp_dir.path().join("go.mod"), "module app").unwrap();
+        assert_eq!(names(temp_dir.path()), vec!["go", "nix"]);
+    }
+
+    #[test]
+    fn test_merged_deduplicates() {
+        let presets = [find("cmake").unwrap(), find("make").unwrap()];
+        let include = merged(&presets, |p| p.include);
+        assert_eq!(include.iter().filter(|i| **i == "*.c").count(), 1);
+        assert!(include.contains(&"Makefile"));
+    }
+}
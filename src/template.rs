@@ -0,0 +1,147 @@
+//! Модуль рендеринга результата по пользовательскому шаблону (синтаксис Jinja).
+use minijinja::value::{Enumerator, Object, Value};
+use minijinja::{Environment, ErrorKind, UndefinedBehavior, context};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::formatter::{self, Entry, Layout};
+use crate::vars::Vars;
+use crate::writer::SNAPSHOT_MARKER;
+
+/// Рендерит шаблон сразу в `writer`; файлы в `files` читаются с диска при обращении
+/// к `content`, `lines` или `hash`, поэтому в памяти одновременно не больше одного файла.
+pub fn render_layout(
+    writer: &mut impl Write,
+    source: &str,
+    vars: &Vars,
+    top: Option<String>,
+    layout: &Layout,
+    down: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let env = environment(source)?;
+    let files: Vec<Value> = layout
+        .entries
+        .iter()
+        .map(|entry| Value::from_object(LazyFile(entry.clone())))
+        .collect();
+    let context = context(vars, top, &layout.tree, files.len(), layout.total_size(), Value::from(files), down);
+    env.get_template("project")?.render_captured_to(context, writer)?;
+    Ok(())
+}
+
+fn environment(source: &str) -> Result<Environment<'_>, minijinja::Error> {
+    let mut env = Environment::new();
+    // Опечатка в имени переменной должна быть ошибкой, а не пустой строкой
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_trim_blocks(true);
+    env.set_keep_trailing_newline(true);
+    env.add_template("project", source)?;
+    Ok(env)
+}
+
+fn context(
+    vars: &Vars,
+    top: Option<String>,
+    tree: &str,
+    file_count: usize,
+    total_size: u64,
+    files: Value,
+    down: Option<String>,
+) -> Value {
+    let generated_at = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    context! {
+        top => top.unwrap_or_default(),
+        down => down.unwrap_or_default(),
+        tree => tree,
+        file_count => file_count,
+        total_size => total_size,
+        files => files,
+        generated_at => generated_at,
+        marker => SNAPSHOT_MARKER,
+        ..Value::from_serialize(vars)
+    }
+}
+
+/// Файл в контексте шаблона, который читается с диска только по требованию.
+struct LazyFile(Entry);
+
+impl std::fmt::Debug for LazyFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LazyFile").field(&self.0.path).finish()
+    }
+}
+
+impl Object for LazyFile {
+    fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+        let entry = &self.0;
+        let key = key.as_str()?;
+        match key {
+            "path" => return Some(Value::from(entry.path.as_str())),
+            "lang" => return Some(Value::from(formatter::language(&entry.path))),
+            "size" => return Some(Value::from(entry.size)),
+            "mode" => return Some(Value::from(entry.mode.to_string())),
+            "content" | "lines" | "hash" => {}
+            _ => return None,
+        }
+        let content = match entry.read() {
+            Ok(content) => content,
+            Err(e) => {
+                return Some(Value::from(minijinja::Error::new(ErrorKind::InvalidOperation, e.to_string())));
+            }
+        };
+        Some(match key {
+            "lines" => Value::from(content.lines().count()),
+            "hash" => Value::from(format!("{:x}", Sha256::digest(content.as_bytes()))),
+            _ => Value::from(content),
+        })
+    }
+
+    fn enumerate(self: &Arc<Self>) -> Enumerator {
+        Enumerator::Str(&["path", "lang", "mode", "lines", "size", "hash", "content"])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("src")).unwrap();
+        let path = temp_dir.path().join("src/main.rs");
+        std::fs::write(&path, "fn main() {}\n").unwrap();
+        let layout = formatter::layout(&[path], &Default::default(), temp_dir.path()).unwrap();
+        let source = "{{ top }}\n{% for file in files %}\n<{{ file.path }} lang={{ file.lang }} mode={{ file.mode }} lines={{ file.lines }}>\n{{ file.content }}</file>\n{% endfor %}\n";
+
+        let mut output = Vec::new();
+        render_layout(&mut output, source, &Vars::new(), Some("Top".to_string()), &layout, None).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Top\n<src/main.rs lang=rust mode=full lines=1>\nfn main() {}\n</file>\n"
+        );
+    }
+
+    #[test]
+    fn test_render_layout_reads_files_lazily() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("main.rs");
+        std::fs::write(&path, "fn main() {}\n").unwrap();
+        let layout = formatter::layout(&[path], &Default::default(), temp_dir.path()).unwrap();
+        let source = "{{ file_count }} {{ total_size }}\n{% for file in files %}\n{{ file.path }} {{ file.lines }} {{ file.hash[:8] }}\n{{ file.content }}{% endfor %}\n";
+
+        let mut output = Vec::new();
+        render_layout(&mut output, source, &Vars::new(), None, &layout, None).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "1 13\nmain.rs 1 536e506b\nfn main() {}\n");
+    }
+
+    #[test]
+    fn test_render_unknown_variable_fails() {
+        let layout = Layout { tree: String::new(), entries: Vec::new() };
+        assert!(render_layout(&mut Vec::new(), "{{ nope }}", &Vars::new(), None, &layout, None).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+//! Модуль иерархического дерева файлов для раздела «Project Structure» и `--tree-only`.
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::collector::{MatchOptions, PatternSet};
+use crate::formatter::{estimate_tokens, human_size};
+
+/// Как рисовать дерево.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Показывать размер, строки и оценку токенов у файлов и итоги у директорий
+    pub annotate: bool,
+    /// Директории глубже этого уровня сворачиваются в `(N files, X KiB)`
+    pub depth: Option<usize>,
+    /// Сколько элементов показывать в одной директории, остальные — строкой `… and N more files`
+    pub max_entries: Option<usize>,
+    /// Директории, которые всегда сворачиваются в `(N files, X KiB)`
+    pub collapse: PatternSet,
+    /// Директории, которые всегда показываются целиком (без `max_entries` и `depth`)
+    pub expand: PatternSet,
+}
+
+/// Компилирует паттерны директорий для `collapse` / `expand` в семантике `[files] matching`.
+///
+/// Завершающий `/` не важен: паттерны и так сравниваются только с директориями.
+pub fn dir_patterns(
+    patterns: &[String],
+    key: &str,
+    options: MatchOptions,
+) -> Result<PatternSet, Box<dyn std::error::Error>> {
+    let trimmed: Vec<String> = patterns.iter().map(|p| p.trim_end_matches('/').to_string()).collect();
+    PatternSet::with_options(&trimmed, options).map_err(|e| format!("[tree] {}: {}", key, e).into())
+}
+
+fn matches_dir(patterns: &PatternSet, path: &str) -> bool {
+    !patterns.is_empty() && patterns.find(Path::new(path)).is_some()
+}
+
+/// Файл в дереве.
+#[derive(Debug, Clone, Default)]
+pub struct Leaf {
+    pub size: u64,
+    /// Число строк; `None`, если не считали
+    pub lines: Option<usize>,
+    /// Цель символической ссылки (`name -> target`)
+    pub link: Option<String>,
+    /// Пометить `*` (содержимое есть в результате, а остальные файлы показаны только для контекста)
+    pub marked: bool,
+}
+
+impl Leaf {
+    /// Файл с размером и числом строк, прочитанными с диска.
+    pub fn from_disk(path: &Path) -> Self {
+        let size = path.metadata().map(|m| m.len()).unwrap_or(0);
+        Self { size, lines: count_lines(path), ..Self::default() }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Tree {
+    root: Dir,
+}
+
+#[derive(Debug, Default)]
+struct Dir {
+    name: String,
+    children: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Dir(Dir),
+    File(String, Leaf),
+}
+
+/// Итоги по директории.
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    files: usize,
+    size: u64,
+    lines: Option<usize>,
+}
+
+impl Tree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавляет файл по относительному пути через `/`; директории создаются по дороге.
+    pub fn insert(&mut self, path: &str, leaf: Leaf) {
+        let mut parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+        let Some(name) = parts.pop() else {
+            return;
+        };
+        let mut dir = &mut self.root;
+        for part in parts {
+            dir = dir.subdir(part);
+        }
+        dir.children.push(Node::File(name.to_string(), leaf));
+    }
+
+    pub fn render(&self, options: &Options) -> String {
+        let mut out = String::new();
+        self.root.render_children(options, "", "", 1, &mut out);
+        out
+    }
+}
+
+impl Dir {
+    fn subdir(&mut self, name: &str) -> &mut Dir {
+        let index = match self
+            .children
+            .iter()
+            .position(|child| matches!(child, Node::Dir(dir) if dir.name == name))
+        {
+            Some(index) => index,
+            None => {
+                self.children.push(Node::Dir(Dir { name: name.to_string(), children: Vec::new() }));
+                self.children.len() - 1
+            }
+        };
+        match &mut self.children[index] {
+            Node::Dir(dir) => dir,
+            Node::File(..) => unreachable!(),
+        }
+    }
+
+    fn totals(&self) -> Totals {
+        let mut totals = Totals { lines: Some(0), ..Totals::default() };
+        for child in &self.children {
+            let child = child.totals();
+            totals.files += child.files;
+            totals.size += child.size;
+            totals.lines = totals.lines.zip(child.lines).map(|(a, b)| a + b);
+        }
+        totals
+    }
+
+    fn render_children(&self, options: &Options, path: &str, prefix: &str, level: usize, out: &mut String) {
+        let expanded = !path.is_empty() && matches_dir(&options.expand, path);
+        let shown = match options.max_entries {
+            Some(max) if !expanded && self.children.len() > max => max,
+            _ => self.children.len(),
+        };
+        let hidden = &self.children[shown..];
+
+        for (index, child) in self.children[..shown].iter().enumerate() {
+            let last = index + 1 == shown && hidden.is_empty();
+            let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+            match child {
+                Node::File(name, leaf) => {
+                    out.push_str(&format!("{}{}{}", prefix, branch, name));
+                    if let Some(target) = &leaf.link {
+                        out.push_str(&format!(" -> {}", target));
+                    }
+                    if leaf.marked {
+                        out.push_str(" *");
+                    }
+                    if options.annotate {
+                        let totals = Totals { files: 1, size: leaf.size, lines: leaf.lines };
+                        out.push_str(&format!(" ({})", describe(&totals, false)));
+                    }
+                    out.push('\n');
+                }
+                Node::Dir(dir) => {
+                    out.push_str(&format!("{}{}{}/", prefix, branch, dir.name));
+                    let dir_path = if path.is_empty() { dir.name.clone() } else { format!("{}/{}", path, dir.name) };
+                    let collapsed = if matches_dir(&options.expand, &dir_path) {
+                        false
+                    } else {
+                        matches_dir(&options.collapse, &dir_path) || options.depth.is_some_and(|depth| level >= depth)
+                    };
+                    if collapsed {
+                        let totals = dir.totals();
+                        out.push_str(&format!(" ({}, {})\n", files(totals.files), human_size(totals.size)));
+                        continue;
+                    }
+                    if options.annotate {
+                        out.push_str(&format!(" ({})", describe(&dir.totals(), true)));
+                    }
+                    out.push('\n');
+                    dir.render_children(options, &dir_path, &format!("{}{}", prefix, indent), level + 1, out);
+                }
+            }
+        }
+
+        if !hidden.is_empty() {
+            let count: usize = hidden.iter().map(|child| child.totals().files).sum();
+            let size: u64 = hidden.iter().map(|child| child.totals().size).sum();
+            let noun = if count == 1 { "file" } else { "files" };
+            out.push_str(&format!("{}└── … and {} more {} ({})\n", prefix, count, noun, human_size(size)));
+        }
+    }
+}
+
+impl Node {
+    fn totals(&self) -> Totals {
+        match self {
+            Node::Dir(dir) => dir.totals(),
+            Node::File(_, leaf) => Totals { files: 1, size: leaf.size, lines: leaf.lines },
+        }
+    }
+}
+
+/// `1 file`, `12 files`.
+fn files(count: usize) -> String {
+    format!("{} file{}", count, if count == 1 { "" } else { "s" })
+}
+
+/// `12 files, 40.1 KiB, 1200 lines, ~10270 tokens`.
+fn describe(totals: &Totals, with_count: bool) -> String {
+    let mut parts = Vec::new();
+    if with_count {
+        parts.push(files(totals.files));
+    }
+    parts.push(human_size(totals.size));
+    if let Some(lines) = totals.lines {
+        parts.push(format!("{} line{}", lines, if lines == 1 { "" } else { "s" }));
+    }
+    parts.push(format!("~{} tokens", estimate_tokens(totals.size)));
+    parts.join(", ")
+}
+
+/// Число строк без загрузки файла целиком (как `str::lines`: последняя строка без `\n` тоже считается).
+fn count_lines(path: &Path) -> Option<usize> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let mut lines = 0;
+    let mut last = b'\n';
+    loop {
+        let buffer = reader.fill_buf().ok()?;
+        if buffer.is_empty() {
+            break;
+        }
+        lines += buffer.iter().filter(|&&b| b == b'\n').count();
+        last = buffer[buffer.len() - 1];
+        let len = buffer.len();
+        reader.consume(len);
+    }
+    Some(if last == b'\n' { lines } else { lines + 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Tree {
+        let mut tree = Tree::new();
+        let leaf = |size, lines| Leaf { size, lines: Some(lines), ..Leaf::default() };
+        tree.insert("Cargo.toml", leaf(100, 5));
+        tree.insert("src/main.rs", leaf(2048, 80));
+        tree.insert("src/cli/args.rs", leaf(1024, 40));
+        tree.insert("src/cli/run.rs", leaf(1024, 40));
+        tree
+    }
+
+    #[test]
+    fn test_render_plain() {
+        let expected = "\
+├── Cargo.toml
+└── src/
+    ├── main.rs
+    └── cli/
+        ├── args.rs
+        └── run.rs
+";
+        assert_eq!(sample().render(&Options::default()), expected);
+    }
+
+    #[test]
+    fn test_render_annotated_with_depth() {
+        let options = Options { annotate: true, depth: Some(2), ..Options::default() };
+        let expected = "\
+├── Cargo.toml (100 B, 5 lines, ~25 tokens)
+└── src/ (3 files, 4.0 KiB, 160 lines, ~1024 tokens)
+    ├── main.rs (2.0 KiB, 80 lines, ~512 tokens)
+    └── cli/ (2 files, 2.0 KiB)
+";
+        assert_eq!(sample().render(&options), expected);
+    }
+
+    #[test]
+    fn test_collapse_large_directories() {
+        let mut tree = Tree::new();
+        for dir in ["db/migrations", "fixtures", "src"] {
+            for index in 0..5 {
+                tree.insert(&format!("{}/{:02}.sql", dir, index), Leaf { size: 1024, ..Leaf::default() });
+            }
+        }
+        let patterns = |items: &[&str]| {
+            let items: Vec<String> = items.iter().map(|s| s.to_string()).collect();
+            dir_patterns(&items, "test", MatchOptions::default()).unwrap()
+        };
+        let options = Options {
+            max_entries: Some(2),
+            collapse: patterns(&["fixtures/"]),
+            expand: patterns(&["db/migrations"]),
+            ..Options::default()
+        };
+        let expected = "\
+├── db/
+│   └── migrations/
+│       ├── 00.sql
+│       ├── 01.sql
+│       ├── 02.sql
+│       ├── 03.sql
+│       └── 04.sql
+├── fixtures/ (5 files, 5.0 KiB)
+└── … and 5 more files (5.0 KiB)
+";
+        assert_eq!(tree.render(&options), expected);
+
+        // Те же правила в семантике `matching = "gitignore"`
+        let gitignore = MatchOptions { matching: crate::collector::Matching::Gitignore, case_insensitive: true };
+        let options = Options {
+            collapse: dir_patterns(&["/FIXTURES/".to_string()], "collapse", gitignore).unwrap(),
+            ..Options::default()
+        };
+        assert!(tree.render(&options).contains("├── fixtures/ (5 files, 5.0 KiB)\n"));
+    }
+}
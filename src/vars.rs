@@ -0,0 +1,86 @@
+//! Модуль переменных для подстановки `{{name}}` в тексты `[top]` / `[down]` и шаблоны.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+pub type Vars = BTreeMap<String, String>;
+
+/// Встроенные переменные: `project_name`, `date`, `git_branch`, `file_count`.
+pub fn builtin(base_path: &Path, file_count: usize) -> Vars {
+    let mut vars = Vars::new();
+    let project_name = base_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    vars.insert("project_name".to_string(), project_name);
+    let now = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    vars.insert("date".to_string(), now[..10].to_string());
+    vars.insert("git_branch".to_string(), git_branch(base_path).unwrap_or_default());
+    vars.insert("file_count".to_string(), file_count.to_string());
+    vars
+}
+
+/// Заменяет `{{name}}` (пробелы внутри скобок допустимы); неизвестные имена остаются как есть.
+pub fn substitute(text: &str, vars: &Vars) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        match vars.get(after[..end].trim()) {
+            Some(value) => result.push_str(value),
+            None => result.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Текущая ветка git без вызова `git`: читаем `.git/HEAD`, поднимаясь по родителям.
+fn git_branch(base_path: &Path) -> Option<String> {
+    let head = base_path
+        .ancestors()
+        .map(|dir| dir.join(".git/HEAD"))
+        .find(|path| path.is_file())?;
+    let head = fs::read_to_string(head).ok()?;
+    let head = head.trim();
+
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(reference.trim_start_matches("refs/heads/").to_string()),
+        // detached HEAD — короткий хеш коммита
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_substitute() {
+        let mut vars = Vars::new();
+        vars.insert("project_name".to_string(), "ginpee".to_string());
+
+        assert_eq!(substitute("# {{project_name}} / {{ project_name }}", &vars), "# ginpee / ginpee");
+        assert_eq!(substitute("{{unknown}} and {{ open", &vars), "{{unknown}} and {{ open");
+    }
+
+    #[test]
+    fn test_git_branch() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join(".git")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("nested")).unwrap();
+        fs::write(temp_dir.path().join(".git/HEAD"), "ref: refs/heads/feature/x\n").unwrap();
+
+        assert_eq!(git_branch(&temp_dir.path().join("nested")).as_deref(), Some("feature/x"));
+    }
+}
@@ -1,30 +1,91 @@
 //! Модуль записи результата в `project.md`.
-use std::fs::File;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
 use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tempfile::{Builder, NamedTempFile};
 
-use crate::formatter::FileContent;
+use crate::cache;
+use crate::collector;
+
+use crate::formatter::{FileContent, Layout};
+use crate::mode::Mode;
+use crate::template;
+use crate::vars::Vars;
+
+/// Первая строка каждого снимка: по ней ginpee узнаёт свои файлы и не включает их в новые.
+pub const SNAPSHOT_MARKER: &str = "<!-- generated by ginpee -->";
 
 pub fn write_project_md(
-    output_path: &std::path::Path,
+    output_path: &Path,
     top: Option<String>,
     tree: &str,
     contents: &[FileContent],
     down: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let file = File::create(output_path)?;
-    let mut writer = BufWriter::new(file);
+    let files = contents
+        .iter()
+        .map(|item| Ok((item.path.as_str(), item.mode, Cow::Borrowed(item.content.as_str()))));
+    write_files(output_path, top, tree, files, down)
+}
+
+/// Пишет встроенный макет, не держа в памяти все файлы сразу.
+///
+/// Тела читаются параллельно пачками по числу потоков (`jobs`, `0` — по числу ядер)
+/// и пишутся в исходном порядке, так что в памяти не больше одной пачки.
+pub fn write_layout(
+    output_path: &Path,
+    top: Option<String>,
+    layout: &Layout,
+    down: Option<String>,
+    jobs: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    let files = layout.entries.chunks(pool.current_num_threads()).flat_map(|chunk| {
+        let bodies: Vec<Result<String, String>> = pool.install(|| {
+            chunk
+                .par_iter()
+                .map(|entry| entry.read().map_err(|e| e.to_string()))
+                .collect()
+        });
+        chunk.iter().zip(bodies).map(|(entry, body)| {
+            body.map(|body| (entry.path.as_str(), entry.mode, Cow::Owned(body))).map_err(Into::into)
+        })
+    });
+    write_files(output_path, top, &layout.tree, files, down)
+}
+
+fn write_files<'a>(
+    output_path: &Path,
+    top: Option<String>,
+    tree: &str,
+    files: impl Iterator<Item = Result<(&'a str, Mode, Cow<'a, str>), Box<dyn std::error::Error>>>,
+    down: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut temp = create_temp(output_path)?;
+    let mut writer = BufWriter::new(temp.as_file_mut());
 
     if let Some(text) = top {
         writeln!(writer, "{}", text)?;
     }
 
+    // Маркер после `[top]`: первой строкой результата остаётся текст пользователя
+    writeln!(writer, "{}", SNAPSHOT_MARKER)?;
     writeln!(writer, "# Project Structure\n")?;
     writeln!(writer, "```\n{}\n```", tree)?;
 
-    for item in contents {
-        writeln!(writer, "\n## File: `{}`\n", item.path)?;
+    for item in files {
+        let (path, mode, content) = item?;
+        // Неполное содержимое помечается, чтобы читатель не принял его за весь файл
+        match mode {
+            Mode::Full => writeln!(writer, "\n## File: `{}`\n", path)?,
+            _ => writeln!(writer, "\n## File: `{}` ({})\n", path, mode)?,
+        }
         writeln!(writer, "```")?;
-        writeln!(writer, "{}", item.content)?;
+        writeln!(writer, "{}", content)?;
         writeln!(writer, "```")?;
     }
 
@@ -32,6 +93,174 @@ pub fn write_project_md(
         writeln!(writer, "\n{}", text)?;
     }
 
+    writer.flush()?;
+    drop(writer);
+    persist(temp, output_path)
+}
+
+/// Рендерит шаблон прямо в файл; содержимое файлов читается, только когда шаблон к нему обращается.
+pub fn write_layout_with_template(
+    output_path: &Path,
+    template_source: &str,
+    vars: &Vars,
+    top: Option<String>,
+    layout: &Layout,
+    down: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut temp = create_temp(output_path)?;
+    let mut writer = BufWriter::new(temp.as_file_mut());
+    template::render_layout(&mut writer, template_source, vars, top, layout, down)?;
+    writer.flush()?;
+    drop(writer);
+    persist(temp, output_path)
+}
+
+/// Проверяет, что результат можно перезаписать: чужой файл — только с `--force`.
+///
+/// Свой файл узнаётся по маркеру, по макету старых версий или по записи в `state_dir`
+/// (результат шаблона может быть без маркера).
+pub fn check_overwrite(output_path: &Path, force: bool, state_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if force
+        || !output_path.exists()
+        || collector::is_snapshot(output_path)
+        || collector::is_legacy_snapshot(output_path)
+        || is_remembered(state_dir, output_path)
+    {
+        return Ok(());
+    }
+    Err(format!(
+        "refusing to overwrite {}: it was not generated by ginpee (use --force to overwrite it)",
+        output_path.display()
+    )
+    .into())
+}
+
+/// Части и другие форматы результата рядом с ним (`project.json`, `project.part1.md` для `project.md`):
+/// файлы `project.*` в той же директории, которые ginpee узнаёт как свои — по маркеру
+/// или по записи в `state_dir`. Остальные (`project.rs`, `Cargo.toml` для `-o Cargo.md`) не трогаются.
+pub fn related_paths(output_path: &Path, state_dir: &Path) -> Vec<PathBuf> {
+    let Some(stem) = output_path.file_stem().map(|stem| format!("{}.", stem.to_string_lossy())) else {
+        return Vec::new();
+    };
+    let dir = match output_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&stem))
+        .map(|entry| dir.join(entry.file_name()))
+        .filter(|path| collector::is_snapshot(path) || is_remembered(state_dir, path))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Пути резервных копий: `project.md.bak` — самая свежая, затем `project.md.bak.1` и т. д.
+pub fn backup_paths(output_path: &Path, count: usize) -> Vec<PathBuf> {
+    let mut name = output_path.as_os_str().to_os_string();
+    name.push(".bak");
+    let newest = PathBuf::from(name);
+    (0..count)
+        .map(|index| match index {
+            0 => newest.clone(),
+            _ => newest.with_extension(format!("bak.{}", index)),
+        })
+        .collect()
+}
+
+/// Сохраняет текущий результат в `project.md.bak`, сдвигая более старые копии (хранится `count`).
+pub fn backup(output_path: &Path, count: usize) -> Result<(), Box<dyn std::error::Error>> {
+    if count == 0 || !output_path.is_file() {
+        return Ok(());
+    }
+    let paths = backup_paths(output_path, count);
+    for pair in paths.windows(2).rev() {
+        if pair[0].exists() {
+            fs::rename(&pair[0], &pair[1])?;
+        }
+    }
+    fs::copy(output_path, &paths[0])
+        .map_err(|e| format!("failed to back up {}: {}", output_path.display(), e))?;
+    Ok(())
+}
+
+/// Хеши записанных результатов в служебной директории.
+const OUTPUTS_FILE: &str = "outputs.json";
+
+/// Запоминает записанный результат, чтобы следующий запуск перезаписал его без `--force`.
+pub fn remember(state_dir: &Path, output_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut outputs = load_outputs(state_dir);
+    outputs.insert(output_key(output_path), file_hash(output_path)?);
+    cache::create_ginpee_dir(state_dir)?;
+    fs::write(state_dir.join(OUTPUTS_FILE), serde_json::to_vec_pretty(&outputs)?)?;
+    Ok(())
+}
+
+/// Файл записан ginpee и с тех пор не менялся.
+fn is_remembered(state_dir: &Path, output_path: &Path) -> bool {
+    let outputs = load_outputs(state_dir);
+    match (outputs.get(&output_key(output_path)), file_hash(output_path)) {
+        (Some(remembered), Ok(hash)) => *remembered == hash,
+        _ => false,
+    }
+}
+
+fn load_outputs(state_dir: &Path) -> BTreeMap<String, String> {
+    fs::read(state_dir.join(OUTPUTS_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn output_key(output_path: &Path) -> String {
+    let path = output_path.canonicalize().unwrap_or_else(|_| output_path.to_path_buf());
+    path.to_string_lossy().into_owned()
+}
+
+fn file_hash(path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Временный файл рядом с результатом: `rename` в пределах одной директории атомарен.
+fn create_temp(output_path: &Path) -> Result<NamedTempFile, Box<dyn std::error::Error>> {
+    let dir = match output_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let name = output_path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let prefix = format!(".{}.", name);
+    let mut builder = Builder::new();
+    builder.prefix(&prefix).suffix(".tmp");
+    // Временные файлы создаются с правами 0600; результат должен остаться таким, как был
+    match fs::metadata(output_path) {
+        Ok(metadata) => {
+            builder.permissions(metadata.permissions());
+        }
+        #[cfg(unix)]
+        Err(_) => {
+            use std::os::unix::fs::PermissionsExt;
+            builder.permissions(fs::Permissions::from_mode(0o644));
+        }
+        #[cfg(not(unix))]
+        Err(_) => {}
+    }
+    builder
+        .tempfile_in(dir)
+        .map_err(|e| format!("failed to create a temporary file in {}: {}", dir.display(), e).into())
+}
+
+/// Заменяет результат готовым временным файлом; при ошибке раньше временный файл просто удаляется.
+fn persist(temp: NamedTempFile, output_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    temp.as_file().sync_all()?;
+    temp.persist(output_path)
+        .map_err(|e| format!("failed to write {}: {}", output_path.display(), e.error))?;
     Ok(())
 }
 
@@ -46,6 +275,7 @@ mod tests {
         let temp_file = NamedTempFile::new().unwrap();
         let contents = vec![FileContent {
             path: "main.rs".to_string(),
+            mode: Mode::Full,
             content: "// test".to_string(),
         }];
 
@@ -64,4 +294,41 @@ mod tests {
         assert!(output.contains("// test"));
         assert!(output.contains("Down text"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_related_paths() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let marked = format!("{}\n# Project Structure", SNAPSHOT_MARKER);
+        for name in ["project.md", "project.part1.md", "project.md.bak", "projects.md"] {
+            fs::write(temp_dir.path().join(name), &marked).unwrap();
+        }
+        for name in ["project.rs", "project.toml", "notes.md"] {
+            fs::write(temp_dir.path().join(name), "source").unwrap();
+        }
+        let state_dir = temp_dir.path().join(".ginpee");
+        fs::write(temp_dir.path().join("project.json"), "{}").unwrap();
+        remember(&state_dir, &temp_dir.path().join("project.json")).unwrap();
+
+        let names: Vec<String> = related_paths(&temp_dir.path().join("project.md"), &state_dir)
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["project.json", "project.md", "project.md.bak", "project.part1.md"]);
+    }
+
+    #[test]
+    fn test_failed_write_keeps_previous_output() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let output = temp_dir.path().join("project.md");
+        fs::write(&output, format!("{}\nold", SNAPSHOT_MARKER)).unwrap();
+        let missing = temp_dir.path().join("missing.rs");
+        let layout = Layout {
+            tree: "missing.rs\n".to_string(),
+            entries: vec![crate::formatter::Entry { path: "missing.rs".to_string(), source: missing, size: 0, mode: Mode::Full, cache: None }],
+        };
+
+        assert!(write_layout(&output, None, &layout, None, 1).is_err());
+        assert_eq!(fs::read_to_string(&output).unwrap(), format!("{}\nold", SNAPSHOT_MARKER));
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+}
@@ -247,6 +247,8 @@ include = [
     assert!(output.contains("NixOS configuration"), "Missing flake.nix content");
 
     println!("✅ All NixOS config files found and validated!");
+
+    std::env::set_current_dir(original_dir).unwrap();
 }
 
 #[test]
@@ -409,3 +411,392 @@ include = [
 
     std::env::set_current_dir(original_dir).unwrap();
 }
+
+#[test]
+#[serial]
+fn test_template_from_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::create_dir_all("templates").unwrap();
+    fs::write("main.rs", "fn main() {}").unwrap();
+    fs::write(
+        "templates/prompt.md",
+        "<tree>\n{{ tree }}</tree>\n{% for file in files %}\n<file path=\"{{ file.path }}\" lang=\"{{ file.lang }}\">\n{{ file.content }}\n</file>\n{% endfor %}\n",
+    )
+    .unwrap();
+    fs::write(
+        "ginpee.toml",
+        r#"
+[files]
+include = ["*.rs"]
+
+[output]
+template = "templates/prompt.md"
+"#,
+    )
+    .unwrap();
+
+    run_command(
+        PathBuf::from("project.md"),
+        None,
+        None,
+        vec![],
+        PathBuf::from("ginpee.toml"),
+    )
+    .unwrap();
+
+    let output = fs::read_to_string("project.md").unwrap();
+    assert!(output.starts_with("<tree>\n"), "{}", output);
+    assert!(output.contains("<file path=\"main.rs\" lang=\"rust\">\nfn main() {}\n</file>"));
+    assert!(!output.contains("# Project Structure"));
+
+    // Результат шаблона без маркера всё равно узнаётся как свой и перезаписывается без --force
+    fs::write("main.rs", "fn main() { run() }").unwrap();
+    ginpee::run(ginpee::RunArgs::default()).unwrap();
+    let output = fs::read_to_string("project.md").unwrap();
+    assert!(output.contains("fn main() { run() }"), "{}", output);
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_top_from_file_with_variables() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::create_dir_all("prompts").unwrap();
+    fs::write("main.rs", "fn main() {}").unwrap();
+    fs::write("prompts/preamble.md", "Project: {{project_name}}, files: {{ file_count }}").unwrap();
+    fs::write("outro.md", "The end of {{project_name}}").unwrap();
+    fs::write(
+        "ginpee.toml",
+        r#"
+[top]
+file = ["prompts/preamble.md"]
+
+[files]
+include = ["*.rs"]
+"#,
+    )
+    .unwrap();
+
+    run_command(
+        PathBuf::from("project.md"),
+        None,
+        Some("@outro.md".to_string()),
+        vec![],
+        PathBuf::from("ginpee.toml"),
+    )
+    .unwrap();
+
+    let project_name = temp_dir.path().file_name().unwrap().to_string_lossy().into_owned();
+    let output = fs::read_to_string("project.md").unwrap();
+    assert!(output.starts_with(&format!("Project: {}, files: 1\n", project_name)));
+    assert!(output.contains(&format!("The end of {}", project_name)));
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_config_interpolation() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::create_dir_all("src").unwrap();
+    fs::write("src/main.rs", "fn main() {}").unwrap();
+    fs::write(
+        "ginpee.toml",
+        r#"
+[vars]
+src = "src"
+job = "${GINPEE_TEST_UNSET_JOB:-local}"
+
+[top]
+text = "Snapshot for {{job}}"
+
+[files]
+include = ["${src}/*.rs"]
+
+[output]
+path = "snapshot-${job}.md"
+"#,
+    )
+    .unwrap();
+
+    ginpee::run(ginpee::RunArgs::default()).unwrap();
+
+    let output = fs::read_to_string("snapshot-local.md").unwrap();
+    assert!(output.contains("Snapshot for local"));
+    assert!(output.contains("fn main() {}"));
+
+    fs::write("ginpee.toml", "[files]\ninclude = [\"${GINPEE_TEST_UNSET_JOB}\"]\n").unwrap();
+    let error = ginpee::run(ginpee::RunArgs::default()).unwrap_err();
+    assert!(error.to_string().contains("undefined variable `GINPEE_TEST_UNSET_JOB` in `files.include[0]`"));
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_output_is_not_included_in_itself() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::write("README.md", "# Readme").unwrap();
+    fs::write("ginpee.toml", "[files]\ninclude = [\"*.md\"]\n").unwrap();
+
+    for _ in 0..3 {
+        run_command(
+            PathBuf::from("project.md"),
+            None,
+            None,
+            vec![],
+            PathBuf::from("ginpee.toml"),
+        )
+        .unwrap();
+    }
+    fs::rename("project.md", "old.md").unwrap();
+    run_command(
+        PathBuf::from("project.md"),
+        None,
+        None,
+        vec![],
+        PathBuf::from("ginpee.toml"),
+    )
+    .unwrap();
+
+    let output = fs::read_to_string("project.md").unwrap();
+    assert_eq!(output.matches("## File:").count(), 1);
+    assert!(output.contains("## File: `README.md`"));
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_sources_named_like_output_are_embedded() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::write("main.rs", "fn main() {}").unwrap();
+    fs::write("main.c", "int main() {}").unwrap();
+    fs::write("ginpee.toml", "[files]\ninclude = [\"*.rs\", \"*.c\"]\n").unwrap();
+
+    let args = ginpee::RunArgs { output: Some(PathBuf::from("main.md")), ..ginpee::RunArgs::default() };
+    ginpee::run(args).unwrap();
+
+    let output = fs::read_to_string("main.md").unwrap();
+    assert!(output.contains("## File: `main.rs`"), "{}", output);
+    assert!(output.contains("## File: `main.c`"), "{}", output);
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_backups_and_foreign_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::write("README.md", "# Readme").unwrap();
+    fs::write("ginpee.toml", "[files]\ninclude = [\"*.md*\"]\n\n[output]\nbackups = 2\n").unwrap();
+
+    // Чужой файл с тем же именем не перезаписывается без --force
+    fs::write("project.md", "my notes").unwrap();
+    let error = ginpee::run(ginpee::RunArgs::default()).unwrap_err();
+    assert!(error.to_string().contains("--force"), "{}", error);
+    assert_eq!(fs::read_to_string("project.md").unwrap(), "my notes");
+
+    let force = ginpee::RunArgs { force: true, ..ginpee::RunArgs::default() };
+    ginpee::run(force).unwrap();
+    assert_eq!(fs::read_to_string("project.md.bak").unwrap(), "my notes");
+
+    for _ in 0..2 {
+        ginpee::run(ginpee::RunArgs::default()).unwrap();
+    }
+    let output = fs::read_to_string("project.md").unwrap();
+    assert_eq!(output.matches("## File:").count(), 1);
+    assert!(fs::read_to_string("project.md.bak").unwrap().contains("## File: `README.md`"));
+    assert!(fs::read_to_string("project.md.bak.1").unwrap().contains("## File: `README.md`"));
+    assert!(!std::path::Path::new("project.md.bak.2").exists());
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_overwrites_snapshot_from_older_version() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::write("main.rs", "fn main() {}").unwrap();
+    fs::write("ginpee.toml", "[files]\ninclude = [\"*.rs\"]\n").unwrap();
+    // Так выглядел project.md до появления маркера: сначала [top], затем дерево
+    fs::write(
+        "project.md",
+        "Intro\n# Project Structure\n\n```\nmain.rs\n\n```\n\n## File: `main.rs`\n\n```\nfn old() {}\n```\n",
+    )
+    .unwrap();
+
+    ginpee::run(ginpee::RunArgs::default()).unwrap();
+
+    let output = fs::read_to_string("project.md").unwrap();
+    assert!(output.contains("fn main() {}"), "{}", output);
+    assert!(!output.contains("fn old() {}"), "{}", output);
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_foreign_structure_file_needs_force() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::write("main.rs", "fn main() {}").unwrap();
+    fs::write("ginpee.toml", "[files]\ninclude = [\"*.rs\", \"*.md\"]\n").unwrap();
+    // Написанный руками файл с тем же заголовком, что и у снимков до маркера
+    let notes = "# Project Structure\n\n```\nsrc/\n```\n\nNotes about the layout\n";
+    fs::write("STRUCTURE.md", notes).unwrap();
+
+    let args = ginpee::RunArgs { output: Some(PathBuf::from("STRUCTURE.md")), ..ginpee::RunArgs::default() };
+    let error = ginpee::run(args).unwrap_err();
+    assert!(error.to_string().contains("--force"), "{}", error);
+    assert_eq!(fs::read_to_string("STRUCTURE.md").unwrap(), notes);
+
+    // И в другие снимки он попадает как обычный файл
+    ginpee::run(ginpee::RunArgs::default()).unwrap();
+    let output = fs::read_to_string("project.md").unwrap();
+    assert!(output.contains("## File: `STRUCTURE.md`"), "{}", output);
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_tree_only() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::create_dir_all("src/cli").unwrap();
+    fs::write("src/main.rs", "fn main() {}\n").unwrap();
+    fs::write("src/cli/args.rs", "pub struct Args;\n").unwrap();
+    fs::write("ginpee.toml", "[files]\ninclude = [\"*.rs\"]\n").unwrap();
+
+    let args = ginpee::RunArgs { tree_only: true, annotate: true, depth: Some(2), ..ginpee::RunArgs::default() };
+    ginpee::run(args).unwrap();
+
+    let output = fs::read_to_string("project.md").unwrap();
+    assert!(output.contains("└── src/ (2 files, 30 B, 2 lines, ~8 tokens)"), "{}", output);
+    assert!(output.contains("├── cli/ (1 file, 17 B)"), "{}", output);
+    assert!(output.contains("└── main.rs (13 B, 1 line, ~4 tokens)"), "{}", output);
+    assert!(!output.contains("## File:"));
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_full_tree_marks_embedded_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::create_dir_all("src").unwrap();
+    fs::create_dir_all("target").unwrap();
+    fs::write("src/main.rs", "fn main() {}\n").unwrap();
+    fs::write("Cargo.lock", "# lock\n").unwrap();
+    fs::write("target/app", "binary").unwrap();
+    fs::write(".gpskip", "target/\n").unwrap();
+    fs::write("ginpee.toml", "[files]\ninclude = [\"*.rs\"]\n\n[tree]\nfull = true\n").unwrap();
+
+    ginpee::run(ginpee::RunArgs::default()).unwrap();
+
+    let output = fs::read_to_string("project.md").unwrap();
+    assert!(output.contains("├── Cargo.lock\n"), "{}", output);
+    assert!(output.contains("    └── main.rs *\n"), "{}", output);
+    assert!(!output.contains("target"), "{}", output);
+    assert!(!output.contains("project.md"), "{}", output);
+    assert_eq!(output.matches("## File:").count(), 1);
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_modes_per_glob() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::create_dir_all("src/handlers").unwrap();
+    fs::create_dir_all("tests").unwrap();
+    fs::write("src/handlers/user.rs", "fn a() {}\nfn b() {}\nfn c() {}\n").unwrap();
+    fs::write("src/db.rs", "fn a() {}\nfn b() {}\nfn c() {}\n").unwrap();
+    fs::write("tests/api.rs", "#[test]\nfn api() {}\n").unwrap();
+    fs::write(
+        "ginpee.toml",
+        r#"[files]
+include = ["**/*.rs"]
+modes = [
+    { glob = "src/handlers/**", mode = "full" },
+    { glob = "src/**", mode = "head:1" },
+    { glob = "tests/**", mode = "name-only" },
+]
+"#,
+    )
+    .unwrap();
+
+    ginpee::run(ginpee::RunArgs::default()).unwrap();
+
+    let output = fs::read_to_string("project.md").unwrap();
+    assert!(output.contains("## File: `src/handlers/user.rs`\n\n```\nfn a() {}\nfn b() {}\nfn c() {}\n"), "{}", output);
+    assert!(output.contains("## File: `src/db.rs` (head:1)\n\n```\nfn a() {}\n… (2 more lines)\n```"), "{}", output);
+    assert!(output.contains("api.rs"), "{}", output);
+    assert!(!output.contains("## File: `tests/api.rs`"), "{}", output);
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_add_edits_manifest_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(temp_dir.path()).unwrap();
+
+    fs::write(
+        "Cargo.toml",
+        "[package]\nname = \"app\"\n\n[package.metadata.ginpee]\ntop = { text = \"Intro\" }\n\n[package.metadata.ginpee.files]\ninclude = [\"*.rs\"]\n",
+    )
+    .unwrap();
+    let target = ginpee::edit::Target { exclude: false, profile: None, table: &[] };
+    ginpee::edit::run_add(None, target, &["*.md".to_string()]).unwrap();
+
+    // Новый ginpee.toml перекрыл бы конфиг из манифеста
+    assert!(!std::path::Path::new("ginpee.toml").exists());
+    let (config, source) = ginpee::config::resolve(None).unwrap();
+    assert_eq!(source, ginpee::config::Source::CargoToml(PathBuf::from("./Cargo.toml")));
+    assert_eq!(config.files.unwrap().include, vec!["*.rs", "*.md"]);
+    assert_eq!(config.top.unwrap().text, "Intro");
+
+    fs::remove_file("Cargo.toml").unwrap();
+    fs::write("package.json", r#"{ "name": "app", "ginpee": { "files": { "include": ["*.ts"] } } }"#).unwrap();
+    let error = ginpee::edit::run_add(None, target, &["*.md".to_string()]).unwrap_err();
+    assert!(error.to_string().contains("package.json"), "{}", error);
+    assert!(!std::path::Path::new("ginpee.toml").exists());
+
+    std::env::set_current_dir(original_dir).unwrap();
+}